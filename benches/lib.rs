@@ -15,7 +15,7 @@ fn bench_get_screen_damage_event(b: &mut Bencher) {
         width: 80,
     });
     vterm.screen_receive_events(&ScreenCallbacksConfig::all());
-    vterm.screen_set_damage_merge(ffi::VTermDamageSize::VTermDamageRow);
+    vterm.screen_set_damage_merge(DamageSize::Row);
     let rx = vterm.screen_event_rx.take().unwrap();
 
     b.iter(|| {
@@ -27,3 +27,52 @@ fn bench_get_screen_damage_event(b: &mut Bencher) {
         }
     });
 }
+
+fn colorful_vterm() -> VTerm {
+    let mut vterm: VTerm = VTerm::new(&Size {
+        height: 24,
+        width: 80,
+    });
+    for i in 0..(24 * 80) {
+        vterm.write(format!("\x1b[38;5;{}m\x1b[48;5;{}mx", i % 256, (i + 128) % 256).as_bytes())
+            .unwrap();
+    }
+    vterm
+}
+
+// A copy of how palette indexes were found before PaletteIndex, by asking libvterm about every
+// entry. The old code path is gone, so this stands in for it as the "before" measurement.
+fn palette_index_by_asking(vterm: &VTerm, target: &ColorRGB) -> Option<ColorPalette> {
+    (0..256).find(|&i| vterm.state_get_rgb_color_from_palette(i) == *target)
+}
+
+#[bench]
+fn bench_read_screen_asking_for_palette_indexes(b: &mut Bencher) {
+    let vterm = colorful_vterm();
+    let rect = Rect::new(Pos::new(0, 0), vterm.get_size());
+
+    b.iter(|| {
+        let cells = vterm.screen_get_cells_in_rect(&rect);
+        for cell in &cells {
+            test::black_box(palette_index_by_asking(&vterm, &cell.fg_rgb));
+            test::black_box(palette_index_by_asking(&vterm, &cell.bg_rgb));
+        }
+        cells
+    });
+}
+
+#[bench]
+fn bench_read_screen(b: &mut Bencher) {
+    let vterm = colorful_vterm();
+    let rect = Rect::new(Pos::new(0, 0), vterm.get_size());
+
+    b.iter(|| vterm.screen_get_cells_in_rect(&rect));
+}
+
+#[bench]
+fn bench_read_screen_snapshot(b: &mut Bencher) {
+    let vterm = colorful_vterm();
+    let mut snapshot = ScreenSnapshot::new();
+
+    b.iter(|| vterm.screen_snapshot_into(&mut snapshot));
+}
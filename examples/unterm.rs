@@ -75,30 +75,42 @@ fn dump_cell(vterm: &VTerm, cell: &ScreenCell, prev_cell: &ScreenCell, context:
             if prev_cell.fg_rgb.red != cell.fg_rgb.red ||
                prev_cell.fg_rgb.green != cell.fg_rgb.green ||
                prev_cell.fg_rgb.blue != cell.fg_rgb.blue {
-                let index = vterm.state_get_palette_color_from_rgb(&cell.fg_rgb);
-                if index < 8 {
-                    sgrs.push(30 + index as usize);
-                } else if index < 16 {
-                    sgrs.push(90 + (index as usize - 8));
-                } else {
-                    sgrs.push(38);
-                    sgrs.push(5 | (1 << 31));
-                    sgrs.push(index as usize | (1 << 31));
+                match vterm.state_get_palette_color_from_rgb(&cell.fg_rgb) {
+                    Some(index) if index < 8 => sgrs.push(30 + index),
+                    Some(index) if index < 16 => sgrs.push(90 + (index - 8)),
+                    Some(index) => {
+                        sgrs.push(38);
+                        sgrs.push(5 | (1 << 31));
+                        sgrs.push(index | (1 << 31));
+                    }
+                    None => {
+                        sgrs.push(38);
+                        sgrs.push(2 | (1 << 31));
+                        sgrs.push(cell.fg_rgb.red as usize | (1 << 31));
+                        sgrs.push(cell.fg_rgb.green as usize | (1 << 31));
+                        sgrs.push(cell.fg_rgb.blue as usize | (1 << 31));
+                    }
                 }
             }
 
             if prev_cell.bg_rgb.red != cell.bg_rgb.red ||
                prev_cell.bg_rgb.green != cell.bg_rgb.green ||
                prev_cell.bg_rgb.blue != cell.bg_rgb.blue {
-                let index = vterm.state_get_palette_color_from_rgb(&cell.bg_rgb);
-                if index < 8 {
-                    sgrs.push(40 + index as usize);
-                } else if index < 16 {
-                    sgrs.push(100 + (index as usize - 8));
-                } else {
-                    sgrs.push(48);
-                    sgrs.push(5 | (1 << 31));
-                    sgrs.push(index as usize | (1 << 31));
+                match vterm.state_get_palette_color_from_rgb(&cell.bg_rgb) {
+                    Some(index) if index < 8 => sgrs.push(40 + index),
+                    Some(index) if index < 16 => sgrs.push(100 + (index - 8)),
+                    Some(index) => {
+                        sgrs.push(48);
+                        sgrs.push(5 | (1 << 31));
+                        sgrs.push(index | (1 << 31));
+                    }
+                    None => {
+                        sgrs.push(48);
+                        sgrs.push(2 | (1 << 31));
+                        sgrs.push(cell.bg_rgb.red as usize | (1 << 31));
+                        sgrs.push(cell.bg_rgb.green as usize | (1 << 31));
+                        sgrs.push(cell.bg_rgb.blue as usize | (1 << 31));
+                    }
                 }
             }
 
@@ -120,7 +132,7 @@ fn dump_cell(vterm: &VTerm, cell: &ScreenCell, prev_cell: &ScreenCell, context:
     }
 
     std::io::stdout()
-        .write_all(&cell.chars)
+        .write_all(cell.text().as_bytes())
         .ok()
         .expect("failed to write");
 }
@@ -234,6 +246,7 @@ fn main() {
             Err(_) => panic!("error reading from file"),
         }
     }
+    vt.flush().unwrap();
 
     while let Ok(event) = rx.try_recv() {
         match event {
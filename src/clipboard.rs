@@ -0,0 +1,48 @@
+use super::*;
+
+/// What applications may do with the clipboard through OSC 52
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum ClipboardPolicy {
+    /// Ignore OSC 52 entirely
+    Deny,
+    /// Let applications set the clipboard, but not read it
+    AllowWrite,
+    /// Let applications set the clipboard and ask for its contents
+    AllowReadWrite,
+}
+
+impl Default for ClipboardPolicy {
+    fn default() -> ClipboardPolicy {
+        ClipboardPolicy::Deny
+    }
+}
+
+impl ClipboardPolicy {
+    pub fn allows_write(&self) -> bool {
+        *self != ClipboardPolicy::Deny
+    }
+
+    pub fn allows_read(&self) -> bool {
+        *self == ClipboardPolicy::AllowReadWrite
+    }
+}
+
+impl VTerm {
+    pub fn clipboard_policy(&self) -> ClipboardPolicy {
+        self.inner.clipboard_policy
+    }
+
+    /// Clipboard access is denied until this is called, since an untrusted program could
+    /// otherwise read the clipboard.
+    pub fn set_clipboard_policy(&mut self, policy: ClipboardPolicy) {
+        self.inner.clipboard_policy = policy;
+    }
+
+    /// Answer a ClipboardQuery by writing the clipboard contents to the output buffer. Does
+    /// nothing unless the policy allows reading.
+    pub fn clipboard_reply(&mut self, selection: &str, data: &[u8]) {
+        if self.inner.clipboard_policy.allows_read() {
+            ::osc::write_clipboard_reply(self.inner.vterm_ptr as *mut ffi::VTerm, selection, data);
+        }
+    }
+}
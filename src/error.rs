@@ -0,0 +1,88 @@
+use std::error;
+use std::fmt;
+use std::string::FromUtf8Error;
+
+use super::*;
+
+#[derive(PartialEq, Debug, Clone)]
+pub enum Error {
+    /// libvterm couldn't allocate a new terminal
+    NewFailed,
+    /// The given position isn't on the screen
+    PosOutOfBounds { pos: Pos, size: Size },
+    /// The given rect isn't entirely on the screen
+    RectOutOfBounds { rect: Rect, size: Size },
+    /// Screen text wasn't valid utf8
+    InvalidUtf8(FromUtf8Error),
+    /// libvterm reported a cursor shape we don't know about
+    UnknownCursorShape(i32),
+    /// libvterm reported a mouse mode we don't know about
+    UnknownMouseMode(i32),
+    /// libvterm reported an underline style we don't know about
+    UnknownUnderline(i32),
+    /// libvterm reported a double height line value we don't know about
+    UnknownDoubleHeight(i32),
+    /// A callback panicked while libvterm was processing input
+    CallbackPanicked(String),
+    /// A palette theme file couldn't be loaded
+    InvalidTheme(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::NewFailed => write!(f, "couldn't create vterm"),
+            Error::PosOutOfBounds { ref pos, ref size } => {
+                write!(f,
+                       "given position out of bounds: size={:?} pos={:?}",
+                       size,
+                       pos)
+            }
+            Error::RectOutOfBounds { ref rect, ref size } => {
+                write!(f,
+                       "given rect out of bounds: size={:?} rect={:?}",
+                       size,
+                       rect)
+            }
+            Error::InvalidUtf8(ref err) => write!(f, "invalid utf8: {}", err),
+            Error::UnknownCursorShape(val) => write!(f, "unknown cursor shape value: {}", val),
+            Error::UnknownMouseMode(val) => write!(f, "unknown mouse mode value: {}", val),
+            Error::UnknownUnderline(val) => write!(f, "unknown underline value: {}", val),
+            Error::UnknownDoubleHeight(val) => {
+                write!(f, "unknown double height value: {}", val)
+            }
+            Error::CallbackPanicked(ref msg) => write!(f, "callback panicked: {}", msg),
+            Error::InvalidTheme(ref msg) => write!(f, "invalid theme: {}", msg),
+        }
+    }
+}
+
+impl error::Error for Error {
+    fn description(&self) -> &str {
+        match *self {
+            Error::NewFailed => "couldn't create vterm",
+            Error::PosOutOfBounds { .. } => "given position out of bounds",
+            Error::RectOutOfBounds { .. } => "given rect out of bounds",
+            Error::InvalidUtf8(_) => "invalid utf8",
+            Error::UnknownCursorShape(_) => "unknown cursor shape value",
+            Error::UnknownMouseMode(_) => "unknown mouse mode value",
+            Error::UnknownUnderline(_) => "unknown underline value",
+            Error::UnknownDoubleHeight(_) => "unknown double height value",
+            Error::CallbackPanicked(_) => "callback panicked",
+            Error::InvalidTheme(_) => "invalid theme",
+        }
+    }
+
+    fn cause(&self) -> Option<&error::Error> {
+        match *self {
+            Error::InvalidUtf8(ref err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<FromUtf8Error> for Error {
+    fn from(err: FromUtf8Error) -> Error {
+        Error::InvalidUtf8(err)
+    }
+}
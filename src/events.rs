@@ -1,4 +1,4 @@
-use {Size, ScreenCell, GlyphInfo, Rect, Pos, ColorRGB, ColorPalette};
+use {Size, ScreenCell, GlyphInfo, LineInfo, Rect, Pos, ColorRGB, ColorPalette, Error};
 
 #[derive(PartialEq, Debug, Clone)]
 pub struct AltScreenEvent {
@@ -60,6 +60,20 @@ pub struct SbPushLineEvent {
 pub struct TitleEvent {
     pub title: String,
 }
+/// The current directory reported with OSC 7
+#[derive(PartialEq, Debug, Clone)]
+pub struct WorkingDirectoryEvent {
+    /// Empty if the uri didn't name a host
+    pub host: String,
+    pub path: String,
+}
+/// A desktop notification requested with OSC 9 or OSC 777
+#[derive(PartialEq, Debug, Clone)]
+pub struct NotificationEvent {
+    /// Only OSC 777 notifications have a title
+    pub title: Option<String>,
+    pub body: String,
+}
 #[derive(PartialEq, Debug, Clone)]
 pub struct PutGlyphEvent {
     pub glyph_info: GlyphInfo,
@@ -112,17 +126,53 @@ pub struct PenFontEvent {
 #[derive(PartialEq, Debug, Clone)]
 pub struct PenForegroundEvent {
     pub rgb: ColorRGB,
-    pub palette: ColorPalette,
+    /// None if the color isn't in the palette
+    pub palette: Option<ColorPalette>,
 }
 #[derive(PartialEq, Debug, Clone)]
 pub struct PenBackgroundEvent {
     pub rgb: ColorRGB,
-    pub palette: ColorPalette,
+    /// None if the color isn't in the palette
+    pub palette: Option<ColorPalette>,
 }
 #[derive(PartialEq, Debug, Clone)]
 pub struct LineInfoEvent {
-    // TODO: add line info stuff here
     pub row: i32,
+    pub new: LineInfo,
+    pub old: LineInfo,
+}
+
+/// A terminal property along with its new value
+#[derive(PartialEq, Debug, Clone)]
+pub enum TermProp {
+    CursorVisible(bool),
+    CursorBlink(bool),
+    AltScreen(bool),
+    Title(String),
+    IconName(String),
+    Reverse(bool),
+    CursorShape(CursorShape),
+    Mouse(MouseMode),
+}
+
+/// A pen attribute along with its new value
+#[derive(PartialEq, Debug, Clone)]
+pub enum PenAttr {
+    Bold(bool),
+    Underline(Underline),
+    Italic(bool),
+    Blink(bool),
+    Reverse(bool),
+    Strike(bool),
+    Font(i32),
+    Foreground {
+        rgb: ColorRGB,
+        palette: Option<ColorPalette>,
+    },
+    Background {
+        rgb: ColorRGB,
+        palette: Option<ColorPalette>,
+    },
 }
 
 #[derive(PartialEq, Debug, Clone)]
@@ -133,12 +183,20 @@ pub enum CursorShape {
 }
 
 impl CursorShape {
+    /// Panics if the value is unknown. Prefer try_from_i32 for values coming from libvterm.
     pub fn from_i32(val: i32) -> CursorShape {
+        match CursorShape::try_from_i32(val) {
+            Ok(v) => v,
+            Err(e) => panic!("{}", e),
+        }
+    }
+
+    pub fn try_from_i32(val: i32) -> Result<CursorShape, Error> {
         match val {
-            1 => CursorShape::Block,
-            2 => CursorShape::Underline,
-            3 => CursorShape::BarLeft,
-            _ => panic!("unknown cursor shape value: {}", val),
+            1 => Ok(CursorShape::Block),
+            2 => Ok(CursorShape::Underline),
+            3 => Ok(CursorShape::BarLeft),
+            _ => Err(Error::UnknownCursorShape(val)),
         }
     }
 }
@@ -152,13 +210,21 @@ pub enum MouseMode {
 }
 
 impl MouseMode {
+    /// Panics if the value is unknown. Prefer try_from_i32 for values coming from libvterm.
     pub fn from_i32(val: i32) -> MouseMode {
+        match MouseMode::try_from_i32(val) {
+            Ok(v) => v,
+            Err(e) => panic!("{}", e),
+        }
+    }
+
+    pub fn try_from_i32(val: i32) -> Result<MouseMode, Error> {
         match val {
-            0 => MouseMode::None,
-            1 => MouseMode::Click,
-            2 => MouseMode::Drag,
-            3 => MouseMode::Move,
-            _ => panic!("unknown mouse mode value: {}", val),
+            0 => Ok(MouseMode::None),
+            1 => Ok(MouseMode::Click),
+            2 => Ok(MouseMode::Drag),
+            3 => Ok(MouseMode::Move),
+            _ => Err(Error::UnknownMouseMode(val)),
         }
     }
 }
@@ -171,12 +237,20 @@ pub enum Underline {
 }
 
 impl Underline {
+    /// Panics if the value is unknown. Prefer try_from_i32 for values coming from libvterm.
     pub fn from_i32(val: i32) -> Underline {
+        match Underline::try_from_i32(val) {
+            Ok(v) => v,
+            Err(e) => panic!("{}", e),
+        }
+    }
+
+    pub fn try_from_i32(val: i32) -> Result<Underline, Error> {
         match val {
-            0 => Underline::None,
-            1 => Underline::Single,
-            2 => Underline::Double,
-            _ => panic!("unknown underline value: {}", val),
+            0 => Ok(Underline::None),
+            1 => Ok(Underline::Single),
+            2 => Ok(Underline::Double),
+            _ => Err(Error::UnknownUnderline(val)),
         }
     }
 }
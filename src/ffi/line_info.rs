@@ -0,0 +1,9 @@
+use libc::c_uint;
+
+pub enum VTermLineInfo {}
+
+extern "C" {
+    // from rusty_shims.c, since the fields are bitfields
+    pub fn vterm_line_info_doublewidth(line_info: *const VTermLineInfo) -> c_uint;
+    pub fn vterm_line_info_doubleheight(line_info: *const VTermLineInfo) -> c_uint;
+}
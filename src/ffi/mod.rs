@@ -4,6 +4,7 @@ mod screen;
 mod state;
 mod vterm;
 mod glyph_info;
+mod line_info;
 
 pub use self::cell::*;
 pub use self::parser::*;
@@ -11,6 +12,7 @@ pub use self::screen::*;
 pub use self::state::*;
 pub use self::vterm::*;
 pub use self::glyph_info::*;
+pub use self::line_info::*;
 
 use libc::{c_int, uint8_t};
 
@@ -31,8 +33,6 @@ pub enum VTermProp {
 
 pub enum VTermValue {}
 
-pub enum VTermLineInfo {}
-
 #[repr(C)]
 #[derive(PartialEq, Debug)]
 pub struct VTermPos {
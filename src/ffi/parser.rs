@@ -2,28 +2,54 @@ use libc::{c_int, c_void, c_char, size_t, c_long, c_uchar};
 
 use super::*;
 
+// These apply to the low 32 bits of the c_long CSI arguments, which is where libvterm keeps them
+// regardless of the size of long.
+
+/// Flag set on CSI arguments that are followed by a `:` separated subparameter
+pub const CSI_ARG_FLAG_MORE: u32 = 1 << 31;
+pub const CSI_ARG_MASK: u32 = !(1 << 31);
+/// Value of a CSI argument that was left out
+pub const CSI_ARG_MISSING: u32 = (1 << 31) - 1;
+
+#[derive(Debug)]
 #[repr(C)]
 pub struct VTermParserCallbacks {
-    pub text: extern "C" fn(bytes: *const c_char, len: size_t, user: *mut c_void) -> c_int,
-    pub control: extern "C" fn(control: c_uchar, user: *mut c_void) -> c_int,
+    pub text: Option<extern "C" fn(bytes: *const c_char, len: size_t, user: *mut c_void) -> c_int>,
+    pub control: Option<extern "C" fn(control: c_uchar, user: *mut c_void) -> c_int>,
+
+    pub escape: Option<extern "C" fn(bytes: *const c_char, len: size_t, user: *mut c_void)
+                                     -> c_int>,
+    pub csi: Option<extern "C" fn(leader: *const c_char,
+                                  args: *const c_long,
+                                  argcount: c_int,
+                                  intermed: *const c_char,
+                                  command: c_char,
+                                  user: *mut c_void)
+                                  -> c_int>,
+    pub osc: Option<extern "C" fn(command: *const c_char, cmdlen: size_t, user: *mut c_void)
+                                  -> c_int>,
+    pub dcs: Option<extern "C" fn(command: *const c_char, cmdlen: size_t, user: *mut c_void)
+                                  -> c_int>,
+    pub resize: Option<extern "C" fn(rows: c_int, cols: c_int, user: *mut c_void) -> c_int>,
+}
 
-    pub escape: extern "C" fn(bytes: *const c_char, lne: size_t, user: *mut c_void) -> c_int,
-    pub csi: extern "C" fn(leader: *const c_char,
-                           args: *const c_long,
-                           argcount: c_int,
-                           intermed: *const c_char,
-                           command: c_char,
-                           user: *mut c_void)
-                           -> c_int,
-    pub osc: extern "C" fn(command: *const c_char, cmdlen: size_t, user: *mut c_void) -> c_int,
-    pub dcs: extern "C" fn(command: *const c_char, cmdlen: size_t, user: *mut c_void) -> c_int,
-    pub resize: extern "C" fn(rows: c_int, cols: c_int, user: *mut c_void) -> c_int,
+impl Default for VTermParserCallbacks {
+    fn default() -> VTermParserCallbacks {
+        VTermParserCallbacks {
+            text: None,
+            control: None,
+            escape: None,
+            csi: None,
+            osc: None,
+            dcs: None,
+            resize: None,
+        }
+    }
 }
 
 extern "C" {
     pub fn vterm_parser_set_callbacks(vt: *mut VTerm,
                                       callbacks: *const VTermParserCallbacks,
-                                      user: *mut c_void)
-                                      -> *mut VTermState;
-    pub fn vterm_parser_get_callbacks(vt: *mut VTerm) -> *mut c_void;
+                                      user: *mut c_void);
+    pub fn vterm_parser_get_cbdata(vt: *mut VTerm) -> *mut c_void;
 }
@@ -1,4 +1,4 @@
-use libc::{c_int, c_void, size_t, uint32_t, c_char};
+use libc::{c_int, c_uchar, c_void, size_t, uint32_t, c_char};
 
 use super::*;
 
@@ -14,6 +14,26 @@ pub enum VTermDamageSize {
 
 pub enum VTermAttrMask {}
 
+/// A screen cell without bitfields, from rusty_shims.c
+#[repr(C)]
+#[derive(Debug, Clone, Default)]
+pub struct VTermSnapshotCell {
+    pub chars: [uint32_t; VTERM_MAX_CHARS_PER_CELL],
+    pub width: c_char,
+    pub bold: c_uchar,
+    pub underline: c_uchar,
+    pub italic: c_uchar,
+    pub blink: c_uchar,
+    pub reverse: c_uchar,
+    pub strike: c_uchar,
+    pub font: c_uchar,
+    pub dwl: c_uchar,
+    pub dhl: c_uchar,
+    pub protected_cell: c_uchar,
+    pub fg: VTermColor,
+    pub bg: VTermColor,
+}
+
 #[derive(Debug)]
 #[repr(C)]
 pub struct VTermScreenCallbacks {
@@ -52,8 +72,7 @@ extern "C" {
 
     pub fn vterm_screen_set_unrecognised_fallbacks(screen: *mut VTermScreen,
                                                    fallbacks: *const VTermParserCallbacks,
-                                                   user: *mut c_void)
-                                                   -> *mut c_void;
+                                                   user: *mut c_void);
     pub fn vterm_screen_get_unrecognised_fbdata(screen: *mut VTermScreen) -> *mut c_void;
 
     pub fn vterm_screen_enable_altscreen(screen: *mut VTermScreen, altscreen: c_int);
@@ -86,6 +105,17 @@ extern "C" {
                                  -> c_int;
 
     pub fn vterm_screen_is_eol(screen: *const VTermScreen, pos: VTermPos) -> c_int;
+    // from the patched screen.c
+    pub fn vterm_screen_get_cell_protected(screen: *const VTermScreen, pos: VTermPos) -> c_int;
+    // from rusty_shims.c
+    pub fn vterm_screen_get_snapshot(screen: *const VTermScreen,
+                                     state: *const VTermState,
+                                     rows: c_int,
+                                     cols: c_int,
+                                     cells: *mut VTermSnapshotCell,
+                                     len: size_t,
+                                     cursor: *mut VTermPos)
+                                     -> c_int;
     pub fn vterm_value_get_boolean(value: *const VTermValue) -> c_int;
     pub fn vterm_value_get_number(value: *const VTermValue) -> c_int;
     pub fn vterm_value_get_string(value: *const VTermValue) -> *const c_char;
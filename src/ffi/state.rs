@@ -1,4 +1,4 @@
-use libc::{c_int, c_void};
+use libc::{c_char, c_int, c_void};
 use super::*;
 
 pub enum VTermState {}
@@ -46,6 +46,8 @@ extern "C" {
                                      callbacks: *const VTermStateCallbacks,
                                      user: *mut c_void);
     pub fn vterm_state_get_cbdata(state: *mut VTermState) -> *mut c_void;
+    // from rusty_shims.c
+    pub fn vterm_state_get_callbacks(state: *const VTermState) -> *const VTermStateCallbacks;
 
     pub fn vterm_state_set_unrecognised_fallbacks(state: *mut VTermState,
                                                   fallbacks: *const VTermParserCallbacks,
@@ -75,6 +77,23 @@ extern "C" {
                                     prop: VTermProp,
                                     val: *mut VTermValue)
                                     -> c_int;
+    // from rusty_shims.c
+    pub fn vterm_state_get_bold_highbright(state: *const VTermState) -> c_int;
+    pub fn vterm_state_set_termprop_boolean(state: *mut VTermState,
+                                            prop: VTermProp,
+                                            boolean: c_int)
+                                            -> c_int;
+    pub fn vterm_state_set_termprop_number(state: *mut VTermState,
+                                           prop: VTermProp,
+                                           number: c_int)
+                                           -> c_int;
+    pub fn vterm_state_set_termprop_string(state: *mut VTermState,
+                                           prop: VTermProp,
+                                           string: *mut c_char)
+                                           -> c_int;
+    pub fn vterm_state_get_penattr_boolean(state: *const VTermState, attr: VTermAttr) -> c_int;
+    pub fn vterm_state_get_penattr_number(state: *const VTermState, attr: VTermAttr) -> c_int;
+    pub fn vterm_state_get_penattr_color(state: *const VTermState, attr: VTermAttr) -> VTermColor;
     pub fn vterm_state_get_lineinfo(state: *const VTermState, row: c_int) -> *const VTermLineInfo;
 }
 
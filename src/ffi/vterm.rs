@@ -1,5 +1,4 @@
 use libc::{c_int, uint32_t, size_t, c_char, c_uchar};
-use super::{VTermModifier, VTermKey};
 
 pub enum VTerm {}
 
@@ -20,18 +19,19 @@ extern "C" {
     pub fn vterm_output_get_buffer_remaining(vt: *const VTerm) -> size_t;
 
     pub fn vterm_output_read(vt: *mut VTerm, buffer: *mut c_char, len: size_t) -> size_t;
+    // from rusty_shims.c
+    pub fn vterm_output_push_bytes(vt: *mut VTerm, bytes: *const c_char, len: size_t);
 
-    pub fn vterm_keyboard_unichar(vt: *mut VTerm, c: uint32_t, modifier: VTermModifier);
-    pub fn vterm_keyboard_key(vt: *mut VTerm, key: VTermKey, modifier: VTermModifier);
+    // key and modifier are plain ints rather than VTermKey and VTermModifier because function
+    // keys and combined modifiers have values that aren't variants of those enums.
+    pub fn vterm_keyboard_unichar(vt: *mut VTerm, c: uint32_t, modifier: c_int);
+    pub fn vterm_keyboard_key(vt: *mut VTerm, key: c_int, modifier: c_int);
 
     pub fn vterm_keyboard_start_paste(vt: *mut VTerm);
     pub fn vterm_keyboard_end_paste(vt: *mut VTerm);
 
-    pub fn vterm_mouse_move(vt: *mut VTerm, row: c_int, col: c_int, modifier: VTermModifier);
-    pub fn vterm_mouse_button(vt: *mut VTerm,
-                              button: c_int,
-                              pressed: bool,
-                              modifier: VTermModifier);
+    pub fn vterm_mouse_move(vt: *mut VTerm, row: c_int, col: c_int, modifier: c_int);
+    pub fn vterm_mouse_button(vt: *mut VTerm, button: c_int, pressed: bool, modifier: c_int);
 }
 
 mod tests {
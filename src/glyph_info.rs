@@ -5,7 +5,8 @@ use super::*;
 
 #[derive(Debug, PartialEq, Clone)]
 pub struct GlyphInfo {
-    pub chars: Vec<u8>,
+    /// The character followed by any combining characters
+    pub chars: Vec<char>,
     pub width: isize,
     pub protected_cell: bool,
     pub dwl: bool, // On a DECDWL or DECDHL line
@@ -20,17 +21,15 @@ impl GlyphInfo {
                                             buf.as_mut_ptr(),
                                             ffi::VTERM_MAX_CHARS_PER_CELL as size_t)
         };
-        let buf: [u8; ffi::VTERM_MAX_CHARS_PER_CELL * 4] = unsafe { ::std::mem::transmute(buf) };
-        let mut chars: Vec<u8> = vec![];
-        chars.extend_from_slice(&buf[0..chars_count as usize * 4]);
+        let chars = ::chars_from_u32(&buf[0..chars_count as usize]);
 
         unsafe {
             GlyphInfo {
                 chars: chars,
                 width: ffi::vterm_glyph_info_width(ptr) as isize,
-                protected_cell: int_to_bool(ffi::vterm_glyph_info_width(ptr) as i32),
-                dwl: int_to_bool(ffi::vterm_glyph_info_width(ptr)),
-                dhl: ffi::vterm_glyph_info_width(ptr) as u8,
+                protected_cell: int_to_bool(ffi::vterm_glyph_info_protected_cell(ptr) as i32),
+                dwl: int_to_bool(ffi::vterm_glyph_info_dwl(ptr) as i32),
+                dhl: ffi::vterm_glyph_info_dhl(ptr) as u8,
             }
         }
     }
@@ -0,0 +1,371 @@
+#![allow(unused_variables)]
+
+use std::sync::mpsc::Sender;
+
+use super::*;
+
+/// Receives screen layer callbacks synchronously, while libvterm is processing input. There is one
+/// method per callback in `VTermScreenCallbacks`. Returning true tells libvterm the callback was
+/// handled. The default implementations do nothing and return false, except for `set_term_prop`
+/// where false means something else.
+pub trait ScreenHandler {
+    fn damage(&mut self, rect: Rect) -> bool {
+        false
+    }
+
+    fn move_rect(&mut self, dest: Rect, src: Rect) -> bool {
+        false
+    }
+
+    fn move_cursor(&mut self, new: Pos, old: Pos, is_visible: bool) -> bool {
+        false
+    }
+
+    /// Returning false vetoes the change, so the state layer doesn't record the new value. The
+    /// screen layer has already switched buffers for `AltScreen` by then, so a veto leaves the two
+    /// out of step. The default returns true.
+    fn set_term_prop(&mut self, prop: TermProp) -> bool {
+        true
+    }
+
+    fn bell(&mut self) -> bool {
+        false
+    }
+
+    fn resize(&mut self, size: Size) -> bool {
+        false
+    }
+
+    /// Called with a line that scrolled off the screen, before it's added to the VTerm's
+    /// scrollback.
+    fn sb_pushline(&mut self, cells: &[ScreenCell]) -> bool {
+        false
+    }
+
+    /// Called with a line that was taken out of the VTerm's scrollback and put back on the screen.
+    fn sb_popline(&mut self, cells: &[ScreenCell]) -> bool {
+        false
+    }
+
+    /// An application set the clipboard with OSC 52. The data has been base64 decoded. Only
+    /// called if the VTerm's clipboard policy allows writes.
+    fn clipboard_set(&mut self, selection: String, data: Vec<u8>) -> bool {
+        false
+    }
+
+    /// An application asked for the clipboard contents with OSC 52. Returning the contents writes
+    /// the reply to the output buffer. Only called if the VTerm's clipboard policy allows reads.
+    fn clipboard_query(&mut self, selection: String) -> Option<Vec<u8>> {
+        None
+    }
+
+    /// A shell integration mark was set with OSC 133 while the cursor was on the given row.
+    fn shell_mark(&mut self, mark: ShellMark, row: usize) -> bool {
+        false
+    }
+
+    /// The application reported its current directory with OSC 7.
+    fn working_directory(&mut self, event: WorkingDirectoryEvent) -> bool {
+        false
+    }
+
+    /// The application asked for a desktop notification with OSC 9 or OSC 777.
+    fn notification(&mut self, event: NotificationEvent) -> bool {
+        false
+    }
+}
+
+/// Receives state layer callbacks synchronously, while libvterm is processing input. There is one
+/// method per callback in `VTermStateCallbacks`. Returning true tells libvterm the callback was
+/// handled. The default implementations do nothing and return false, except for `set_term_prop`
+/// and `set_line_info` where false means something else.
+pub trait StateHandler {
+    fn put_glyph(&mut self, glyph_info: &GlyphInfo, pos: Pos) -> bool {
+        false
+    }
+
+    fn move_cursor(&mut self, new: Pos, old: Pos, is_visible: bool) -> bool {
+        false
+    }
+
+    fn scroll_rect(&mut self, rect: Rect, downward: isize, rightward: isize) -> bool {
+        false
+    }
+
+    fn move_rect(&mut self, dest: Rect, src: Rect) -> bool {
+        false
+    }
+
+    fn erase(&mut self, rect: Rect, is_selective: bool) -> bool {
+        false
+    }
+
+    fn init_pen(&mut self) -> bool {
+        false
+    }
+
+    fn set_pen_attr(&mut self, attr: PenAttr) -> bool {
+        false
+    }
+
+    /// Returning false vetoes the change, so the state doesn't record the new value. The default
+    /// returns true.
+    fn set_term_prop(&mut self, prop: TermProp) -> bool {
+        true
+    }
+
+    fn bell(&mut self) -> bool {
+        false
+    }
+
+    fn resize(&mut self, size: Size) -> bool {
+        false
+    }
+
+    /// Returning false vetoes the change, so `VTerm::line_info` keeps the old value. The default
+    /// returns true.
+    fn set_line_info(&mut self, row: i32, new: LineInfo, old: LineInfo) -> bool {
+        true
+    }
+}
+
+/// Receives sequences the state layer didn't understand, synchronously while libvterm is
+/// processing input. Returning true tells libvterm the sequence was handled.
+pub trait UnhandledSequenceHandler {
+    fn handle(&mut self, seq: UnhandledSequence) -> bool;
+}
+
+/// Sends a ScreenEvent down a channel for each callback. This is what `screen_receive_events`
+/// uses.
+pub struct ChannelScreenHandler {
+    tx: Sender<ScreenEvent>,
+}
+
+impl ChannelScreenHandler {
+    pub fn new(tx: Sender<ScreenEvent>) -> ChannelScreenHandler {
+        ChannelScreenHandler { tx: tx }
+    }
+
+    fn send(&self, event: ScreenEvent) -> bool {
+        self.tx.send(event).is_ok()
+    }
+}
+
+impl ScreenHandler for ChannelScreenHandler {
+    fn damage(&mut self, rect: Rect) -> bool {
+        self.send(ScreenEvent::Damage(DamageEvent { rect: rect }))
+    }
+
+    fn move_rect(&mut self, dest: Rect, src: Rect) -> bool {
+        self.send(ScreenEvent::MoveRect(MoveRectEvent {
+            dest: dest,
+            src: src,
+        }))
+    }
+
+    fn move_cursor(&mut self, new: Pos, old: Pos, is_visible: bool) -> bool {
+        self.send(ScreenEvent::MoveCursor(MoveCursorEvent {
+            new: new,
+            old: old,
+            is_visible: is_visible,
+        }))
+    }
+
+    fn set_term_prop(&mut self, prop: TermProp) -> bool {
+        let event = match prop {
+            TermProp::CursorVisible(val) => {
+                ScreenEvent::CursorVisible(CursorVisibleEvent { is_on: val })
+            }
+            TermProp::CursorBlink(val) => ScreenEvent::CursorBlink(CursorBlinkEvent { is_on: val }),
+            TermProp::AltScreen(val) => ScreenEvent::AltScreen(AltScreenEvent { is_on: val }),
+            TermProp::Title(val) => ScreenEvent::Title(TitleEvent { title: val }),
+            TermProp::IconName(val) => ScreenEvent::IconName(IconNameEvent { name: val }),
+            TermProp::Reverse(val) => ScreenEvent::Reverse(ReverseEvent { is_on: val }),
+            TermProp::CursorShape(val) => ScreenEvent::CursorShape(CursorShapeEvent { shape: val }),
+            TermProp::Mouse(val) => ScreenEvent::Mouse(MouseEvent { mode: val }),
+        };
+        // A dropped receiver shouldn't veto the change
+        self.send(event);
+        true
+    }
+
+    fn bell(&mut self) -> bool {
+        self.send(ScreenEvent::Bell)
+    }
+
+    fn resize(&mut self, size: Size) -> bool {
+        self.send(ScreenEvent::Resize(ResizeEvent { size: size }))
+    }
+
+    fn sb_pushline(&mut self, cells: &[ScreenCell]) -> bool {
+        self.send(ScreenEvent::SbPushLine(SbPushLineEvent { cells: cells.to_vec() }))
+    }
+
+    fn sb_popline(&mut self, cells: &[ScreenCell]) -> bool {
+        self.send(ScreenEvent::SbPopLine(SbPopLineEvent { cells: cells.to_vec() }))
+    }
+
+    fn clipboard_set(&mut self, selection: String, data: Vec<u8>) -> bool {
+        self.send(ScreenEvent::ClipboardSet {
+            selection: selection,
+            data: data,
+        })
+    }
+
+    /// Sends a ClipboardQuery event. The reply has to be given later with
+    /// `VTerm::clipboard_reply`.
+    fn clipboard_query(&mut self, selection: String) -> Option<Vec<u8>> {
+        self.send(ScreenEvent::ClipboardQuery { selection: selection });
+        None
+    }
+
+    fn shell_mark(&mut self, mark: ShellMark, row: usize) -> bool {
+        self.send(ScreenEvent::ShellMark {
+            mark: mark,
+            row: row,
+        })
+    }
+
+    fn working_directory(&mut self, event: WorkingDirectoryEvent) -> bool {
+        self.send(ScreenEvent::WorkingDirectory(event))
+    }
+
+    fn notification(&mut self, event: NotificationEvent) -> bool {
+        self.send(ScreenEvent::Notification(event))
+    }
+}
+
+/// Sends a StateEvent down a channel for each callback. This is what `state_receive_events` uses.
+pub struct ChannelStateHandler {
+    tx: Sender<StateEvent>,
+}
+
+impl ChannelStateHandler {
+    pub fn new(tx: Sender<StateEvent>) -> ChannelStateHandler {
+        ChannelStateHandler { tx: tx }
+    }
+
+    fn send(&self, event: StateEvent) -> bool {
+        self.tx.send(event).is_ok()
+    }
+}
+
+impl StateHandler for ChannelStateHandler {
+    fn put_glyph(&mut self, glyph_info: &GlyphInfo, pos: Pos) -> bool {
+        self.send(StateEvent::PutGlyph(PutGlyphEvent {
+            glyph_info: glyph_info.clone(),
+            pos: pos,
+        }))
+    }
+
+    fn move_cursor(&mut self, new: Pos, old: Pos, is_visible: bool) -> bool {
+        self.send(StateEvent::MoveCursor(MoveCursorEvent {
+            new: new,
+            old: old,
+            is_visible: is_visible,
+        }))
+    }
+
+    fn scroll_rect(&mut self, rect: Rect, downward: isize, rightward: isize) -> bool {
+        self.send(StateEvent::ScrollRect(ScrollRectEvent {
+            rect: rect,
+            downward: downward,
+            rightward: rightward,
+        }))
+    }
+
+    fn move_rect(&mut self, dest: Rect, src: Rect) -> bool {
+        self.send(StateEvent::MoveRect(MoveRectEvent {
+            src: src,
+            dest: dest,
+        }))
+    }
+
+    fn erase(&mut self, rect: Rect, is_selective: bool) -> bool {
+        self.send(StateEvent::Erase(EraseEvent {
+            rect: rect,
+            is_selective: is_selective,
+        }))
+    }
+
+    fn init_pen(&mut self) -> bool {
+        self.send(StateEvent::InitPen(InitPenEvent))
+    }
+
+    fn set_pen_attr(&mut self, attr: PenAttr) -> bool {
+        let event = match attr {
+            PenAttr::Bold(val) => StateEvent::PenBold(PenBoldEvent { is_on: val }),
+            PenAttr::Underline(val) => StateEvent::PenUnderline(PenUnderlineEvent { mode: val }),
+            PenAttr::Italic(val) => StateEvent::PenItalic(PenItalicEvent { is_on: val }),
+            PenAttr::Blink(val) => StateEvent::PenBlink(PenBlinkEvent { is_on: val }),
+            PenAttr::Reverse(val) => StateEvent::PenReverse(PenReverseEvent { is_on: val }),
+            PenAttr::Strike(val) => StateEvent::PenStrike(PenStrikeEvent { is_on: val }),
+            PenAttr::Font(val) => StateEvent::PenFont(PenFontEvent { font: val }),
+            PenAttr::Foreground { rgb, palette } => {
+                StateEvent::PenForeground(PenForegroundEvent {
+                    rgb: rgb,
+                    palette: palette,
+                })
+            }
+            PenAttr::Background { rgb, palette } => {
+                StateEvent::PenBackground(PenBackgroundEvent {
+                    rgb: rgb,
+                    palette: palette,
+                })
+            }
+        };
+        self.send(event)
+    }
+
+    fn set_term_prop(&mut self, prop: TermProp) -> bool {
+        let event = match prop {
+            TermProp::CursorVisible(val) => {
+                StateEvent::CursorVisible(CursorVisibleEvent { is_on: val })
+            }
+            TermProp::CursorBlink(val) => StateEvent::CursorBlink(CursorBlinkEvent { is_on: val }),
+            TermProp::AltScreen(val) => StateEvent::AltScreen(AltScreenEvent { is_on: val }),
+            TermProp::Title(val) => StateEvent::Title(TitleEvent { title: val }),
+            TermProp::IconName(val) => StateEvent::IconName(IconNameEvent { name: val }),
+            TermProp::Reverse(val) => StateEvent::Reverse(ReverseEvent { is_on: val }),
+            TermProp::CursorShape(val) => StateEvent::CursorShape(CursorShapeEvent { shape: val }),
+            TermProp::Mouse(val) => StateEvent::Mouse(MouseEvent { mode: val }),
+        };
+        self.send(event);
+        true
+    }
+
+    fn bell(&mut self) -> bool {
+        self.send(StateEvent::Bell(BellEvent))
+    }
+
+    fn resize(&mut self, size: Size) -> bool {
+        self.send(StateEvent::Resize(ResizeEvent { size: size }))
+    }
+
+    fn set_line_info(&mut self, row: i32, new: LineInfo, old: LineInfo) -> bool {
+        self.send(StateEvent::LineInfo(LineInfoEvent {
+            row: row,
+            new: new,
+            old: old,
+        }));
+        true
+    }
+}
+
+/// Sends each UnhandledSequence down a channel. This is what `unhandled_sequence_receive_events`
+/// uses.
+pub struct ChannelUnhandledSequenceHandler {
+    tx: Sender<UnhandledSequence>,
+}
+
+impl ChannelUnhandledSequenceHandler {
+    pub fn new(tx: Sender<UnhandledSequence>) -> ChannelUnhandledSequenceHandler {
+        ChannelUnhandledSequenceHandler { tx: tx }
+    }
+}
+
+impl UnhandledSequenceHandler for ChannelUnhandledSequenceHandler {
+    fn handle(&mut self, seq: UnhandledSequence) -> bool {
+        self.tx.send(seq).is_ok()
+    }
+}
@@ -0,0 +1,75 @@
+use super::*;
+
+/// Identifies a hyperlink within one VTerm. Look it up with `VTerm::hyperlink`.
+pub type LinkId = usize;
+
+/// A hyperlink set by an application with OSC 8.
+#[derive(Debug, PartialEq, Eq, Hash, Clone)]
+pub struct Hyperlink {
+    pub uri: String,
+    /// The `key=value` pairs from the sequence's params, in the order they were given
+    pub params: Vec<(String, String)>,
+}
+
+impl Hyperlink {
+    /// Parse OSC 8 params, which look like `id=foo:bar=baz`. Pairs without an `=` are ignored.
+    pub fn parse_params(params: &str) -> Vec<(String, String)> {
+        params.split(':')
+            .filter_map(|pair| {
+                let mut parts = pair.splitn(2, '=');
+                match (parts.next(), parts.next()) {
+                    (Some(key), Some(value)) if !key.is_empty() => {
+                        Some((key.to_string(), value.to_string()))
+                    }
+                    _ => None,
+                }
+            })
+            .collect()
+    }
+
+    /// The value of the named param, if it was given.
+    pub fn param(&self, key: &str) -> Option<&str> {
+        self.params.iter().find(|&&(ref k, _)| k == key).map(|&(_, ref v)| &v[..])
+    }
+
+    /// Applications use the `id` param to say separate runs of cells are the same link.
+    pub fn id(&self) -> Option<&str> {
+        self.param("id")
+    }
+}
+
+impl VTerm {
+    /// Returns the hyperlink for an id found in `ScreenCell::link`. Links nothing on the screen or
+    /// in the scrollback uses any more are eventually forgotten, and their ids return None.
+    pub fn hyperlink(&self, id: LinkId) -> Option<&Hyperlink> {
+        self.inner.tracker.hyperlink(id)
+    }
+
+    /// The hyperlink cells are currently being written with, if any.
+    pub fn active_hyperlink(&self) -> Option<LinkId> {
+        self.inner.tracker.active_link
+    }
+}
+
+mod tests {
+    #![allow(unused_imports)]
+    use super::super::*;
+
+    #[test]
+    fn hyperlink_parses_params() {
+        let params = Hyperlink::parse_params("id=foo:bar=a=b:junk:=x");
+        assert_eq!(params,
+                   vec![("id".to_string(), "foo".to_string()),
+                        ("bar".to_string(), "a=b".to_string())]);
+
+        let link = Hyperlink {
+            uri: "http://example.com".to_string(),
+            params: params,
+        };
+        assert_eq!(link.id(), Some("foo"));
+        assert_eq!(link.param("bar"), Some("a=b"));
+        assert_eq!(link.param("junk"), None);
+
+        assert!(Hyperlink::parse_params("").is_empty());
+    }
+}
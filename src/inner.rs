@@ -0,0 +1,138 @@
+use libc::{c_int, c_void};
+use std::panic::{self, AssertUnwindSafe};
+use std::ptr;
+use std::sync::mpsc;
+
+use super::*;
+use tracker::Tracker;
+
+/// How many lines of scrollback a new VTerm keeps
+pub const DEFAULT_SCROLLBACK_LIMIT: usize = 1000;
+
+/// The parts of a VTerm that libvterm callbacks need. A pointer to this is given to libvterm as the
+/// callback user data, so it lives on the heap where its address won't change when the VTerm that
+/// owns it is moved.
+pub struct Inner {
+    pub vterm_ptr: *const ffi::VTerm,
+    pub state_ptr: *const ffi::VTermState,
+
+    pub parser_callbacks: Option<ffi::VTermParserCallbacks>,
+    pub parser_event_tx: Option<mpsc::Sender<ParserEvent>>,
+
+    pub screen_callbacks: Option<ffi::VTermScreenCallbacks>,
+    /// Which screen callbacks are passed on to the screen handler
+    pub screen_callbacks_config: ScreenCallbacksConfig,
+    pub screen_handler: Option<Box<ScreenHandler>>,
+    pub scrollback: Scrollback,
+
+    pub state_callbacks: Option<ffi::VTermStateCallbacks>,
+    pub state_handler: Option<Box<StateHandler>>,
+
+    /// Registered in front of the screen layer's state callbacks, see tracking_callbacks
+    pub tracking_callbacks: Option<ffi::VTermStateCallbacks>,
+    pub screen_state_callbacks: *const ffi::VTermStateCallbacks,
+    pub screen_state_cbdata: *mut c_void,
+    pub tracker: Tracker,
+
+    pub unhandled_callbacks: Option<ffi::VTermParserCallbacks>,
+    pub unhandled_sequence_handler: Option<Box<UnhandledSequenceHandler>>,
+
+    pub clipboard_policy: ClipboardPolicy,
+    /// Looks up palette indexes for the colors libvterm gives us
+    pub palette_index: PaletteIndex,
+
+    /// The first error that happened inside a callback, waiting to be returned by the next write.
+    pub error: Option<Error>,
+}
+
+impl Inner {
+    pub fn new(vterm_ptr: *const ffi::VTerm, state_ptr: *const ffi::VTermState) -> Inner {
+        Inner {
+            vterm_ptr: vterm_ptr,
+            state_ptr: state_ptr,
+            parser_callbacks: None,
+            parser_event_tx: None,
+            screen_callbacks: None,
+            screen_callbacks_config: ScreenCallbacksConfig::none(),
+            screen_handler: None,
+            scrollback: Scrollback::new(DEFAULT_SCROLLBACK_LIMIT),
+            state_callbacks: None,
+            state_handler: None,
+            tracking_callbacks: None,
+            screen_state_callbacks: ptr::null(),
+            screen_state_cbdata: ptr::null_mut(),
+            tracker: Tracker::new(),
+            unhandled_callbacks: None,
+            unhandled_sequence_handler: None,
+            clipboard_policy: Default::default(),
+            palette_index: Default::default(),
+            error: None,
+        }
+    }
+
+    pub fn take_error(&mut self) -> Option<Error> {
+        self.error.take()
+    }
+}
+
+/// Call the closure with the Inner behind the callback user data pointer. Unwinding into
+/// libvterm's C frames is undefined behaviour, so a panic is caught here and stored as an error
+/// instead, and 0 is returned to libvterm.
+pub fn with_inner<F>(inner: *mut c_void, closure: F) -> c_int
+    where F: FnOnce(&mut Inner) -> c_int
+{
+    let result = {
+        let inner: &mut Inner = unsafe { &mut *(inner as *mut Inner) };
+        panic::catch_unwind(AssertUnwindSafe(|| closure(inner)))
+    };
+
+    match result {
+        Ok(ret) => ret,
+        Err(payload) => {
+            let msg = if let Some(msg) = payload.downcast_ref::<&str>() {
+                msg.to_string()
+            } else if let Some(msg) = payload.downcast_ref::<String>() {
+                msg.clone()
+            } else {
+                "unknown panic".to_string()
+            };
+
+            let inner: &mut Inner = unsafe { &mut *(inner as *mut Inner) };
+            if inner.error.is_none() {
+                inner.error = Some(Error::CallbackPanicked(msg));
+            }
+            0
+        }
+    }
+}
+
+mod tests {
+    #![allow(unused_imports)]
+    use super::*;
+    use std::ptr;
+
+    #[test]
+    fn with_inner_catches_panics() {
+        let mut inner = Box::new(Inner::new(ptr::null(), ptr::null()));
+        let inner_ptr: *mut c_void = &mut *inner as *mut _ as *mut c_void;
+
+        let ret = with_inner(inner_ptr, |_| panic!("boom"));
+        assert_eq!(ret, 0);
+
+        let ret = with_inner(inner_ptr, |_| panic!("second boom"));
+        assert_eq!(ret, 0);
+
+        assert_eq!(inner.take_error(),
+                   Some(Error::CallbackPanicked("boom".to_string())));
+        assert_eq!(inner.take_error(), None);
+    }
+
+    #[test]
+    fn with_inner_returns_closure_value() {
+        let mut inner = Box::new(Inner::new(ptr::null(), ptr::null()));
+        let inner_ptr: *mut c_void = &mut *inner as *mut _ as *mut c_void;
+
+        assert_eq!(with_inner(inner_ptr, |_| 1), 1);
+        assert_eq!(inner.take_error(), None);
+    }
+}
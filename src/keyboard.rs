@@ -0,0 +1,316 @@
+use libc::{c_int, uint32_t};
+use std::ops::{BitOr, BitOrAssign};
+
+use super::*;
+
+/// Keyboard modifiers, combined like bitflags: `MOD_SHIFT | MOD_CTRL`.
+#[derive(PartialEq, Eq, Debug, Clone, Copy, Default)]
+pub struct Modifiers {
+    bits: u8,
+}
+
+pub const MOD_NONE: Modifiers = Modifiers { bits: 0x00 };
+pub const MOD_SHIFT: Modifiers = Modifiers { bits: 0x01 };
+pub const MOD_ALT: Modifiers = Modifiers { bits: 0x02 };
+pub const MOD_CTRL: Modifiers = Modifiers { bits: 0x04 };
+
+impl Modifiers {
+    pub fn empty() -> Modifiers {
+        MOD_NONE
+    }
+
+    pub fn all() -> Modifiers {
+        MOD_SHIFT | MOD_ALT | MOD_CTRL
+    }
+
+    pub fn bits(&self) -> u8 {
+        self.bits
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bits == 0
+    }
+
+    pub fn contains(&self, other: Modifiers) -> bool {
+        self.bits & other.bits == other.bits
+    }
+
+    pub fn insert(&mut self, other: Modifiers) {
+        self.bits |= other.bits;
+    }
+
+    pub fn remove(&mut self, other: Modifiers) {
+        self.bits &= !other.bits;
+    }
+
+    pub fn as_c_int(&self) -> c_int {
+        self.bits as c_int
+    }
+}
+
+impl BitOr for Modifiers {
+    type Output = Modifiers;
+
+    fn bitor(self, other: Modifiers) -> Modifiers {
+        Modifiers { bits: self.bits | other.bits }
+    }
+}
+
+impl BitOrAssign for Modifiers {
+    fn bitor_assign(&mut self, other: Modifiers) {
+        self.bits |= other.bits;
+    }
+}
+
+/// Non-character keys, mirroring `VTermKey` in vterm_keycodes.h
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum Key {
+    Enter,
+    Tab,
+    Backspace,
+    Escape,
+
+    Up,
+    Down,
+    Left,
+    Right,
+
+    Ins,
+    Del,
+    Home,
+    End,
+    PageUp,
+    PageDown,
+
+    /// Function keys F1 to F255. libvterm only has encodings for F1 to F12 and ignores the rest.
+    Function(u8),
+
+    Kp0,
+    Kp1,
+    Kp2,
+    Kp3,
+    Kp4,
+    Kp5,
+    Kp6,
+    Kp7,
+    Kp8,
+    Kp9,
+    KpMult,
+    KpPlus,
+    KpComma,
+    KpMinus,
+    KpPeriod,
+    KpDivide,
+    KpEnter,
+    KpEqual,
+}
+
+impl Key {
+    pub fn as_c_int(&self) -> c_int {
+        use ffi::VTermKey::*;
+
+        let key = match *self {
+            Key::Enter => VTERM_KEY_ENTER,
+            Key::Tab => VTERM_KEY_TAB,
+            Key::Backspace => VTERM_KEY_BACKSPACE,
+            Key::Escape => VTERM_KEY_ESCAPE,
+            Key::Up => VTERM_KEY_UP,
+            Key::Down => VTERM_KEY_DOWN,
+            Key::Left => VTERM_KEY_LEFT,
+            Key::Right => VTERM_KEY_RIGHT,
+            Key::Ins => VTERM_KEY_INS,
+            Key::Del => VTERM_KEY_DEL,
+            Key::Home => VTERM_KEY_HOME,
+            Key::End => VTERM_KEY_END,
+            Key::PageUp => VTERM_KEY_PAGEUP,
+            Key::PageDown => VTERM_KEY_PAGEDOWN,
+            Key::Function(n) => return VTERM_KEY_FUNCTION_0 as c_int + n as c_int,
+            Key::Kp0 => VTERM_KEY_KP_0,
+            Key::Kp1 => VTERM_KEY_KP_1,
+            Key::Kp2 => VTERM_KEY_KP_2,
+            Key::Kp3 => VTERM_KEY_KP_3,
+            Key::Kp4 => VTERM_KEY_KP_4,
+            Key::Kp5 => VTERM_KEY_KP_5,
+            Key::Kp6 => VTERM_KEY_KP_6,
+            Key::Kp7 => VTERM_KEY_KP_7,
+            Key::Kp8 => VTERM_KEY_KP_8,
+            Key::Kp9 => VTERM_KEY_KP_9,
+            Key::KpMult => VTERM_KEY_KP_MULT,
+            Key::KpPlus => VTERM_KEY_KP_PLUS,
+            Key::KpComma => VTERM_KEY_KP_COMMA,
+            Key::KpMinus => VTERM_KEY_KP_MINUS,
+            Key::KpPeriod => VTERM_KEY_KP_PERIOD,
+            Key::KpDivide => VTERM_KEY_KP_DIVIDE,
+            Key::KpEnter => VTERM_KEY_KP_ENTER,
+            Key::KpEqual => VTERM_KEY_KP_EQUAL,
+        };
+
+        key as c_int
+    }
+}
+
+impl VTerm {
+    /// Send a key press to the terminal. libvterm encodes it according to the current cursor and
+    /// keypad modes and puts the result in the output buffer.
+    pub fn keyboard_key(&mut self, key: Key, modifiers: Modifiers) {
+        unsafe {
+            ffi::vterm_keyboard_key(self.ptr.get_mut(), key.as_c_int(), modifiers.as_c_int());
+        }
+    }
+
+    /// Send a character typed on the keyboard to the terminal. The encoding ends up in the output
+    /// buffer.
+    pub fn keyboard_char(&mut self, c: char, modifiers: Modifiers) {
+        unsafe {
+            ffi::vterm_keyboard_unichar(self.ptr.get_mut(),
+                                        c as uint32_t,
+                                        modifiers.as_c_int());
+        }
+    }
+
+    /// Send pasted text to the terminal. When the application has enabled bracketed paste the
+    /// text is wrapped in paste markers. Newlines are sent as carriage returns, like a key press of
+    /// Enter would be.
+    pub fn paste(&mut self, text: &str) {
+        unsafe { ffi::vterm_keyboard_start_paste(self.ptr.get_mut()) };
+
+        let mut chars = text.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '\r' => {
+                    if chars.peek() == Some(&'\n') {
+                        chars.next();
+                    }
+                    self.keyboard_char('\r', MOD_NONE);
+                }
+                '\n' => self.keyboard_char('\r', MOD_NONE),
+                _ => self.keyboard_char(c, MOD_NONE),
+            }
+        }
+
+        unsafe { ffi::vterm_keyboard_end_paste(self.ptr.get_mut()) };
+    }
+}
+
+mod tests {
+    #![allow(unused_imports)]
+    #![allow(dead_code)]
+
+    use super::super::*;
+    use std::io::prelude::*;
+
+    fn read_output(vterm: &mut VTerm) -> Vec<u8> {
+        let mut bytes = vec![];
+        vterm.read_to_end(&mut bytes).unwrap();
+        bytes
+    }
+
+    #[test]
+    fn modifiers_can_be_combined() {
+        let mods = MOD_SHIFT | MOD_CTRL;
+        assert!(mods.contains(MOD_SHIFT));
+        assert!(mods.contains(MOD_CTRL));
+        assert!(!mods.contains(MOD_ALT));
+        assert_eq!(mods.bits(), 0x05);
+        assert!(Modifiers::empty().is_empty());
+    }
+
+    #[test]
+    fn key_can_be_converted_to_c_int() {
+        assert_eq!(Key::Enter.as_c_int(), 1);
+        assert_eq!(Key::Function(1).as_c_int(), 257);
+        assert_eq!(Key::Function(255).as_c_int(), 511);
+        assert_eq!(Key::Kp0.as_c_int(), 512);
+        assert_eq!(Key::KpEqual.as_c_int(), 529);
+    }
+
+    #[test]
+    fn keyboard_key_writes_cursor_keys() {
+        let mut vterm: VTerm = VTerm::new(&Size {
+            height: 2,
+            width: 2,
+        });
+        vterm.keyboard_key(Key::Up, MOD_NONE);
+        assert_eq!(read_output(&mut vterm), b"\x1b[A");
+
+        vterm.keyboard_key(Key::Up, MOD_CTRL);
+        assert_eq!(read_output(&mut vterm), b"\x1b[1;5A");
+    }
+
+    #[test]
+    fn keyboard_key_honours_application_cursor_mode() {
+        let mut vterm: VTerm = VTerm::new(&Size {
+            height: 2,
+            width: 2,
+        });
+        // DECCKM
+        vterm.write(b"\x1b[?1h").unwrap();
+        vterm.keyboard_key(Key::Up, MOD_NONE);
+        assert_eq!(read_output(&mut vterm), b"\x1bOA");
+    }
+
+    #[test]
+    fn keyboard_key_writes_function_keys() {
+        let mut vterm: VTerm = VTerm::new(&Size {
+            height: 2,
+            width: 2,
+        });
+        vterm.keyboard_key(Key::Function(5), MOD_NONE);
+        assert_eq!(read_output(&mut vterm), b"\x1b[15~");
+    }
+
+    #[test]
+    fn keyboard_key_honours_application_keypad_mode() {
+        let mut vterm: VTerm = VTerm::new(&Size {
+            height: 2,
+            width: 2,
+        });
+        vterm.keyboard_key(Key::Kp5, MOD_NONE);
+        assert_eq!(read_output(&mut vterm), b"5");
+
+        // DECKPAM
+        vterm.write(b"\x1b=").unwrap();
+        vterm.keyboard_key(Key::Kp5, MOD_NONE);
+        assert_eq!(read_output(&mut vterm), b"\x1bOu");
+    }
+
+    #[test]
+    fn keyboard_char_writes_chars() {
+        let mut vterm: VTerm = VTerm::new(&Size {
+            height: 2,
+            width: 2,
+        });
+        vterm.keyboard_char('a', MOD_NONE);
+        assert_eq!(read_output(&mut vterm), b"a");
+
+        vterm.keyboard_char('é', MOD_NONE);
+        assert_eq!(read_output(&mut vterm), "é".as_bytes());
+
+        vterm.keyboard_char('c', MOD_CTRL);
+        assert_eq!(read_output(&mut vterm), b"\x03");
+
+        vterm.keyboard_char('x', MOD_ALT);
+        assert_eq!(read_output(&mut vterm), b"\x1bx");
+    }
+
+    #[test]
+    fn paste_writes_plain_text_without_bracketed_paste() {
+        let mut vterm: VTerm = VTerm::new(&Size {
+            height: 2,
+            width: 2,
+        });
+        vterm.paste("ls\ncd /\r\npwd");
+        assert_eq!(read_output(&mut vterm), b"ls\rcd /\rpwd");
+    }
+
+    #[test]
+    fn paste_writes_markers_with_bracketed_paste() {
+        let mut vterm: VTerm = VTerm::new(&Size {
+            height: 2,
+            width: 2,
+        });
+        vterm.write(b"\x1b[?2004h").unwrap();
+        vterm.paste("a\nb");
+        assert_eq!(read_output(&mut vterm), b"\x1b[200~a\rb\x1b[201~");
+    }
+}
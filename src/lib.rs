@@ -9,22 +9,57 @@ use libc::c_int;
 
 pub mod ffi;
 
+mod base64;
+mod clipboard;
+mod error;
 mod events;
 mod geom;
 mod glyph_info;
+mod handlers;
+mod hyperlink;
+mod inner;
+mod keyboard;
+mod line_info;
+mod mouse;
+mod osc;
+mod palette;
+mod parser;
+mod parser_callbacks;
+mod pen;
 mod screen;
 mod screen_callbacks;
 mod screen_cell;
+mod scrollback;
 mod state;
+mod shell_integration;
+mod snapshot;
 mod state_callbacks;
+mod tracker;
+mod tracking_callbacks;
+mod unhandled;
+mod unhandled_callbacks;
 mod vterm;
 
+pub use clipboard::*;
+pub use error::*;
 pub use events::*;
 pub use geom::*;
 pub use glyph_info::*;
+pub use handlers::*;
+pub use hyperlink::*;
+pub use keyboard::*;
+pub use line_info::*;
+pub use mouse::*;
+pub use palette::*;
+pub use parser::*;
+pub use pen::*;
 pub use screen::*;
 pub use screen_cell::*;
+pub use scrollback::*;
+pub use shell_integration::*;
+pub use snapshot::*;
 pub use state::*;
+pub use unhandled::*;
 pub use vterm::*;
 
 pub fn int_to_bool(val: c_int) -> bool {
@@ -40,3 +75,16 @@ pub fn bool_to_int(flag: bool) -> c_int {
         false => 0,
     }
 }
+
+/// libvterm gives characters as u32 code points. Ones that aren't valid chars are dropped.
+fn chars_from_u32(codes: &[u32]) -> Vec<char> {
+    codes.iter().filter_map(|&code| char_from_u32(code)).collect()
+}
+
+fn char_from_u32(code: u32) -> Option<char> {
+    let c = ::std::char::from_u32(code);
+    if c.is_none() {
+        warn!("invalid code point from libvterm: {:#x}", code);
+    }
+    c
+}
@@ -0,0 +1,73 @@
+use libc::c_int;
+
+use super::*;
+
+/// Which half of a double height line (DECDHL) a row is.
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum DoubleHeight {
+    None = 0,
+    Top,
+    Bottom,
+}
+
+impl DoubleHeight {
+    pub fn try_from_i32(val: i32) -> Result<DoubleHeight, Error> {
+        match val {
+            0 => Ok(DoubleHeight::None),
+            1 => Ok(DoubleHeight::Top),
+            2 => Ok(DoubleHeight::Bottom),
+            _ => Err(Error::UnknownDoubleHeight(val)),
+        }
+    }
+}
+
+/// How a row is drawn, as set by DECDWL and DECDHL.
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub struct LineInfo {
+    /// Set for double height lines too, since they are double width as well
+    pub double_width: bool,
+    pub double_height: DoubleHeight,
+}
+
+impl LineInfo {
+    pub fn from_ptr(ptr: *const ffi::VTermLineInfo) -> Result<LineInfo, Error> {
+        let double_height = unsafe { ffi::vterm_line_info_doubleheight(ptr) };
+        Ok(LineInfo {
+            double_width: int_to_bool(unsafe { ffi::vterm_line_info_doublewidth(ptr) } as i32),
+            double_height: try!(DoubleHeight::try_from_i32(double_height as i32)),
+        })
+    }
+}
+
+impl Default for LineInfo {
+    fn default() -> LineInfo {
+        LineInfo {
+            double_width: false,
+            double_height: DoubleHeight::None,
+        }
+    }
+}
+
+impl VTerm {
+    /// Panics if the row is off the screen. Use try_line_info to get an error instead.
+    pub fn line_info(&self, row: usize) -> LineInfo {
+        match self.try_line_info(row) {
+            Ok(line_info) => line_info,
+            Err(e) => panic!("{}", e),
+        }
+    }
+
+    pub fn try_line_info(&self, row: usize) -> Result<LineInfo, Error> {
+        let size = self.get_size();
+        if row >= size.height {
+            return Err(Error::PosOutOfBounds {
+                pos: Pos::new(0, row),
+                size: size,
+            });
+        }
+
+        LineInfo::from_ptr(unsafe {
+            ffi::vterm_state_get_lineinfo(self.state_ptr.get(), row as c_int)
+        })
+    }
+}
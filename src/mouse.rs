@@ -0,0 +1,110 @@
+use libc::c_int;
+
+use super::*;
+
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum MouseButton {
+    Left = 1,
+    Middle,
+    Right,
+    WheelUp,
+    WheelDown,
+}
+
+impl MouseButton {
+    pub fn as_c_int(&self) -> c_int {
+        *self as c_int
+    }
+}
+
+impl VTerm {
+    /// Tell the terminal the mouse has moved to the given cell. If the application asked for drag
+    /// or move reporting, the report ends up in the output buffer.
+    pub fn mouse_move(&mut self, pos: &Pos, modifiers: Modifiers) {
+        unsafe {
+            ffi::vterm_mouse_move(self.ptr.get_mut(),
+                                  pos.y as c_int,
+                                  pos.x as c_int,
+                                  modifiers.as_c_int());
+        }
+    }
+
+    /// Tell the terminal a mouse button was pressed or released at the last position given to
+    /// `mouse_move`. The report, encoded in the protocol the application asked for, ends up in the
+    /// output buffer.
+    pub fn mouse_button(&mut self, button: MouseButton, pressed: bool, modifiers: Modifiers) {
+        unsafe {
+            ffi::vterm_mouse_button(self.ptr.get_mut(),
+                                    button.as_c_int(),
+                                    pressed,
+                                    modifiers.as_c_int());
+        }
+    }
+}
+
+mod tests {
+    #![allow(unused_imports)]
+    #![allow(dead_code)]
+
+    use super::super::*;
+    use std::io::prelude::*;
+
+    fn read_output(vterm: &mut VTerm) -> Vec<u8> {
+        let mut bytes = vec![];
+        vterm.read_to_end(&mut bytes).unwrap();
+        bytes
+    }
+
+    #[test]
+    fn mouse_button_reports_clicks() {
+        let mut vterm: VTerm = VTerm::new(&Size {
+            height: 5,
+            width: 5,
+        });
+        // click reporting with the SGR protocol
+        vterm.write(b"\x1b[?1000h\x1b[?1006h").unwrap();
+
+        vterm.mouse_move(&Pos::new(2, 1), MOD_NONE);
+        vterm.mouse_button(MouseButton::Left, true, MOD_NONE);
+        assert_eq!(read_output(&mut vterm), b"\x1b[<0;3;2M");
+
+        vterm.mouse_button(MouseButton::Left, false, MOD_NONE);
+        assert_eq!(read_output(&mut vterm), b"\x1b[<0;3;2m");
+
+        vterm.mouse_button(MouseButton::Right, true, MOD_CTRL);
+        assert_eq!(read_output(&mut vterm), b"\x1b[<18;3;2M");
+    }
+
+    #[test]
+    fn mouse_button_reports_wheel() {
+        let mut vterm: VTerm = VTerm::new(&Size {
+            height: 5,
+            width: 5,
+        });
+        vterm.write(b"\x1b[?1000h\x1b[?1006h").unwrap();
+
+        vterm.mouse_move(&Pos::new(0, 0), MOD_NONE);
+        vterm.mouse_button(MouseButton::WheelUp, true, MOD_NONE);
+        assert_eq!(read_output(&mut vterm), b"\x1b[<64;1;1M");
+
+        vterm.mouse_button(MouseButton::WheelDown, true, MOD_NONE);
+        assert_eq!(read_output(&mut vterm), b"\x1b[<65;1;1M");
+    }
+
+    #[test]
+    fn mouse_move_reports_drags() {
+        let mut vterm: VTerm = VTerm::new(&Size {
+            height: 5,
+            width: 5,
+        });
+        // drag reporting with the SGR protocol
+        vterm.write(b"\x1b[?1002h\x1b[?1006h").unwrap();
+
+        vterm.mouse_move(&Pos::new(0, 0), MOD_NONE);
+        vterm.mouse_button(MouseButton::Left, true, MOD_NONE);
+        read_output(&mut vterm);
+
+        vterm.mouse_move(&Pos::new(1, 0), MOD_NONE);
+        assert_eq!(read_output(&mut vterm), b"\x1b[<32;2;1M");
+    }
+}
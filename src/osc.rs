@@ -0,0 +1,262 @@
+// Built in handling for OSC commands libvterm doesn't know about. These get the first look at
+// libvterm's unrecognised OSC fallback, before any UnhandledSequenceHandler.
+
+use libc::{c_char, size_t};
+
+use super::*;
+use inner::Inner;
+
+/// Returns true if the command was handled here.
+pub fn handle(inner: &mut Inner, command: &[u8]) -> bool {
+    let (number, args) = split_command(command);
+    match number {
+        Some(7) => working_directory(inner, args),
+        Some(8) => hyperlink(inner, args),
+        Some(9) => notification(inner, args),
+        Some(52) => clipboard(inner, args),
+        Some(133) => shell_mark(inner, args),
+        Some(777) => notify(inner, args),
+        _ => false,
+    }
+}
+
+/// Split `52;c;Zm9v` into the command number and the rest.
+fn split_command(command: &[u8]) -> (Option<u32>, &[u8]) {
+    let end = command.iter().position(|&b| b == b';').unwrap_or(command.len());
+    let number = ::std::str::from_utf8(&command[..end]).ok().and_then(|s| s.parse().ok());
+    let args = if end < command.len() {
+        &command[end + 1..]
+    } else {
+        &command[end..]
+    };
+    (number, args)
+}
+
+/// OSC 7 ; file://host/path
+fn working_directory(inner: &mut Inner, args: &[u8]) -> bool {
+    let uri = String::from_utf8_lossy(args);
+    let event = match parse_file_uri(&uri) {
+        Some(event) => event,
+        None => {
+            warn!("OSC 7 with a uri that isn't file://: {:?}", uri);
+            return true;
+        }
+    };
+
+    if inner.screen_callbacks_config.working_directory {
+        if let Some(handler) = inner.screen_handler.as_mut() {
+            handler.working_directory(event);
+        }
+    }
+    true
+}
+
+/// Split `file://host/some%20path` into its host and decoded path
+fn parse_file_uri(uri: &str) -> Option<WorkingDirectoryEvent> {
+    if !uri.starts_with("file://") {
+        return None;
+    }
+
+    let rest = &uri["file://".len()..];
+    let path_start = rest.find('/').unwrap_or(rest.len());
+    let path = percent_decode(rest[path_start..].as_bytes());
+    Some(WorkingDirectoryEvent {
+        host: rest[..path_start].to_string(),
+        path: String::from_utf8_lossy(&path).into_owned(),
+    })
+}
+
+/// Decode %XX escapes, leaving malformed ones as they are
+fn percent_decode(bytes: &[u8]) -> Vec<u8> {
+    let hex = |b: u8| (b as char).to_digit(16);
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' && i + 2 < bytes.len() {
+            if let (Some(hi), Some(lo)) = (hex(bytes[i + 1]), hex(bytes[i + 2])) {
+                decoded.push((hi * 16 + lo) as u8);
+                i += 3;
+                continue;
+            }
+        }
+        decoded.push(bytes[i]);
+        i += 1;
+    }
+    decoded
+}
+
+/// OSC 9 ; message is iTerm2's notification. ConEmu uses OSC 9 ; number ; ... for other things,
+/// which are left for the unhandled sequence handler.
+fn notification(inner: &mut Inner, args: &[u8]) -> bool {
+    let (number, _) = split_command(args);
+    if number.is_some() {
+        return false;
+    }
+
+    send_notification(inner,
+                      NotificationEvent {
+                          title: None,
+                          body: String::from_utf8_lossy(args).into_owned(),
+                      });
+    true
+}
+
+/// OSC 777 ; notify ; title ; body is urxvt's notification. Other OSC 777 commands aren't
+/// handled here.
+fn notify(inner: &mut Inner, args: &[u8]) -> bool {
+    let mut parts = args.splitn(3, |&b| b == b';');
+    if parts.next() != Some(&b"notify"[..]) {
+        return false;
+    }
+
+    let title = String::from_utf8_lossy(parts.next().unwrap_or(b"")).into_owned();
+    let body = String::from_utf8_lossy(parts.next().unwrap_or(b"")).into_owned();
+    send_notification(inner,
+                      NotificationEvent {
+                          title: Some(title),
+                          body: body,
+                      });
+    true
+}
+
+fn send_notification(inner: &mut Inner, event: NotificationEvent) {
+    if inner.screen_callbacks_config.notifications {
+        if let Some(handler) = inner.screen_handler.as_mut() {
+            handler.notification(event);
+        }
+    }
+}
+
+/// OSC 8 ; params ; uri starts writing cells with a link, and an empty uri stops it
+fn hyperlink(inner: &mut Inner, args: &[u8]) -> bool {
+    let mut parts = args.splitn(2, |&b| b == b';');
+    let params = String::from_utf8_lossy(parts.next().unwrap_or(b"")).into_owned();
+    let uri = match parts.next() {
+        Some(uri) => String::from_utf8_lossy(uri).into_owned(),
+        None => {
+            warn!("OSC 8 without a uri");
+            return true;
+        }
+    };
+
+    if inner.tracker.needs_sweep() {
+        inner.tracker.sweep(&inner.scrollback);
+    }
+    inner.tracker.active_link = if uri.is_empty() {
+        None
+    } else {
+        Some(inner.tracker.intern(Hyperlink {
+            uri: uri,
+            params: Hyperlink::parse_params(&params),
+        }))
+    };
+    true
+}
+
+/// OSC 133 ; A, B, C or D. The mark is recorded against the cursor's row.
+fn shell_mark(inner: &mut Inner, args: &[u8]) -> bool {
+    let mark = match ShellMark::parse(args) {
+        Some(mark) => mark,
+        None => {
+            warn!("unknown OSC 133 mark {:?}", String::from_utf8_lossy(args));
+            return true;
+        }
+    };
+
+    let mut pos = ffi::VTermPos { row: 0, col: 0 };
+    unsafe { ffi::vterm_state_get_cursorpos(inner.state_ptr, &mut pos) };
+    let pos = pos.as_pos();
+
+    inner.tracker.add_mark(&pos, mark);
+
+    if inner.screen_callbacks_config.shell_marks {
+        if let Some(handler) = inner.screen_handler.as_mut() {
+            handler.shell_mark(mark, pos.y);
+        }
+    }
+    true
+}
+
+/// OSC 52 ; selection ; base64 data, or ? to ask for the clipboard contents
+fn clipboard(inner: &mut Inner, args: &[u8]) -> bool {
+    let mut parts = args.splitn(2, |&b| b == b';');
+    let selection = String::from_utf8_lossy(parts.next().unwrap_or(b"")).into_owned();
+    let data = match parts.next() {
+        Some(data) => data,
+        None => {
+            warn!("OSC 52 without data");
+            return true;
+        }
+    };
+
+    let policy = inner.clipboard_policy;
+    let is_enabled = inner.screen_callbacks_config.clipboard;
+
+    if data == b"?" {
+        if !policy.allows_read() || !is_enabled {
+            return true;
+        }
+
+        let reply = match inner.screen_handler.as_mut() {
+            Some(handler) => handler.clipboard_query(selection.clone()),
+            None => None,
+        };
+        if let Some(reply) = reply {
+            write_clipboard_reply(inner.vterm_ptr as *mut ffi::VTerm, &selection, &reply);
+        }
+        return true;
+    }
+
+    if !policy.allows_write() || !is_enabled {
+        return true;
+    }
+
+    let data = match base64::decode(data) {
+        Some(data) => data,
+        None => {
+            warn!("OSC 52 with invalid base64 data");
+            return true;
+        }
+    };
+    if let Some(handler) = inner.screen_handler.as_mut() {
+        handler.clipboard_set(selection, data);
+    }
+    true
+}
+
+/// Push an OSC 52 reply into the output buffer
+pub fn write_clipboard_reply(vterm_ptr: *mut ffi::VTerm, selection: &str, data: &[u8]) {
+    let reply = format!("\x1b]52;{};{}\x1b\\", selection, base64::encode(data));
+    unsafe {
+        ffi::vterm_output_push_bytes(vterm_ptr,
+                                     reply.as_ptr() as *const c_char,
+                                     reply.len() as size_t)
+    };
+}
+
+mod tests {
+    #![allow(unused_imports)]
+    use super::*;
+
+    #[test]
+    fn osc_parses_file_uris() {
+        assert_eq!(parse_file_uri("file://box/a%2Fb/%e2%9c%93%zz"),
+                   Some(WorkingDirectoryEvent {
+                       host: "box".to_string(),
+                       path: "/a/b/\u{2713}%zz".to_string(),
+                   }));
+        assert_eq!(parse_file_uri("file://box"),
+                   Some(WorkingDirectoryEvent {
+                       host: "box".to_string(),
+                       path: "".to_string(),
+                   }));
+        assert_eq!(parse_file_uri("/tmp"), None);
+    }
+
+    #[test]
+    fn osc_splits_the_command_number() {
+        assert_eq!(split_command(b"52;c;Zm9v"), (Some(52), &b"c;Zm9v"[..]));
+        assert_eq!(split_command(b"7"), (Some(7), &b""[..]));
+        assert_eq!(split_command(b"x;y"), (None, &b"y"[..]));
+    }
+}
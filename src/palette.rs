@@ -0,0 +1,316 @@
+use libc::c_int;
+use std::collections::HashMap;
+
+use super::*;
+
+/// The colors a terminal draws with. Indexed colors from SGR come from `colors`, which has 16
+/// entries for a theme or 256 when read from a VTerm.
+#[derive(PartialEq, Debug, Clone)]
+pub struct Palette {
+    pub colors: Vec<ColorRGB>,
+    pub default_fg: ColorRGB,
+    pub default_bg: ColorRGB,
+}
+
+impl Palette {
+    /// Override colors with the ones set in an Xresources file, like `*.color1: #cc241d` or
+    /// `URxvt*foreground: rgb:eb/db/b2`. `#define`s are expanded and other resources are ignored.
+    pub fn load_xresources(&mut self, text: &str) -> Result<(), Error> {
+        let mut defines: HashMap<&str, &str> = HashMap::new();
+
+        for (i, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.starts_with("#define") {
+                let mut parts = line["#define".len()..].split_whitespace();
+                if let (Some(name), Some(value)) = (parts.next(), parts.next()) {
+                    defines.insert(name, value);
+                }
+                continue;
+            }
+            if line.is_empty() || line.starts_with('!') || line.starts_with('#') {
+                continue;
+            }
+
+            let mut parts = line.splitn(2, ':');
+            let (key, value) = match (parts.next(), parts.next()) {
+                (Some(key), Some(value)) => (key.trim(), value.trim()),
+                _ => continue,
+            };
+            // The resource name is whatever comes after the class and instance parts
+            let name = key.rsplit(|c| c == '.' || c == '*').next().unwrap_or(key);
+            let value = defines.get(value).cloned().unwrap_or(value);
+
+            let color = || {
+                parse_x_color(value).ok_or_else(|| {
+                    Error::InvalidTheme(format!("line {}: bad color {:?}", i + 1, value))
+                })
+            };
+            if name == "foreground" {
+                self.default_fg = try!(color());
+            } else if name == "background" {
+                self.default_bg = try!(color());
+            } else if name.starts_with("color") {
+                if let Ok(index) = name["color".len()..].parse::<usize>() {
+                    if index < self.colors.len() {
+                        self.colors[index] = try!(color());
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Override the first 16 colors and the defaults with a base16 scheme, mapped to ANSI colors
+    /// the same way base16-shell does. All of base00 to base0F must be given.
+    pub fn load_base16_yaml(&mut self, text: &str) -> Result<(), Error> {
+        let mut bases: [Option<ColorRGB>; 16] = Default::default();
+
+        for (i, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let mut parts = line.splitn(2, ':');
+            let (key, value) = match (parts.next(), parts.next()) {
+                (Some(key), Some(value)) => (key.trim(), value.trim()),
+                _ => continue,
+            };
+            if !key.starts_with("base") {
+                continue;
+            }
+            let index = match u8::from_str_radix(&key["base".len()..], 16) {
+                Ok(index) if index < 16 && key.len() == 6 => index as usize,
+                _ => continue,
+            };
+
+            let value = value.split(" #").next().unwrap_or(value).trim().trim_matches('"');
+            let value = value.trim_matches('\'').trim_left_matches('#');
+            bases[index] = match parse_hex_color(value) {
+                Some(color) => Some(color),
+                None => {
+                    return Err(Error::InvalidTheme(format!("line {}: bad color {:?}",
+                                                           i + 1,
+                                                           value)))
+                }
+            };
+        }
+
+        let mut colors = vec![];
+        for (i, base) in bases.iter().enumerate() {
+            match *base {
+                Some(ref color) => colors.push(color.clone()),
+                None => return Err(Error::InvalidTheme(format!("base{:02X} is missing", i))),
+            }
+        }
+
+        let ansi = [0x00, 0x08, 0x0B, 0x0A, 0x0D, 0x0E, 0x0C, 0x05, 0x03, 0x08, 0x0B, 0x0A, 0x0D,
+                    0x0E, 0x0C, 0x07];
+        for (i, &base) in ansi.iter().enumerate().take(self.colors.len()) {
+            self.colors[i] = colors[base].clone();
+        }
+        self.default_fg = colors[0x05].clone();
+        self.default_bg = colors[0x00].clone();
+
+        Ok(())
+    }
+}
+
+/// Finds the palette index of a color without asking libvterm for all 256 entries every time.
+/// It's built from the palette, so it has to be rebuilt whenever the palette changes.
+#[derive(Debug, Default, Clone)]
+pub struct PaletteIndex {
+    indexes: HashMap<(u8, u8, u8), ColorPalette>,
+}
+
+impl PaletteIndex {
+    pub fn new(state_ptr: *const ffi::VTermState) -> PaletteIndex {
+        let mut indexes = HashMap::new();
+        // Backwards so the first entry wins when a color is in the palette twice
+        for i in (0..256).rev() {
+            let mut color: ffi::VTermColor = Default::default();
+            unsafe { ffi::vterm_state_get_palette_color(state_ptr, i as c_int, &mut color) };
+            indexes.insert((color.red, color.green, color.blue), i as ColorPalette);
+        }
+        PaletteIndex { indexes: indexes }
+    }
+
+    /// Returns the index of the first palette entry with the given color, or None if there isn't
+    /// one, as for truecolor and most default colors.
+    pub fn get(&self, rgb: &ColorRGB) -> Option<ColorPalette> {
+        self.indexes.get(&(rgb.red, rgb.green, rgb.blue)).cloned()
+    }
+}
+
+/// `rrggbb`
+fn parse_hex_color(value: &str) -> Option<ColorRGB> {
+    // Checking the bytes first means slicing below can't split a multibyte char
+    if value.len() != 6 || !value.bytes().all(|b| (b as char).is_digit(16)) {
+        return None;
+    }
+    let channel = |i: usize| u8::from_str_radix(&value[i..i + 2], 16).ok();
+    match (channel(0), channel(2), channel(4)) {
+        (Some(red), Some(green), Some(blue)) => {
+            Some(ColorRGB {
+                red: red,
+                green: green,
+                blue: blue,
+            })
+        }
+        _ => None,
+    }
+}
+
+/// The X11 color forms themes use: `#rgb`, `#rrggbb` and `rgb:r/g/b` with 1 to 4 hex digits per
+/// channel.
+fn parse_x_color(value: &str) -> Option<ColorRGB> {
+    if value.starts_with('#') {
+        let digits = &value[1..];
+        return match digits.len() {
+            3 => {
+                let doubled: String = digits.chars().flat_map(|c| vec![c, c]).collect();
+                parse_hex_color(&doubled)
+            }
+            6 => parse_hex_color(digits),
+            _ => None,
+        };
+    }
+
+    if value.starts_with("rgb:") {
+        let parts: Vec<&str> = value["rgb:".len()..].split('/').collect();
+        let channels: Vec<u8> = parts.iter().filter_map(|part| scale_channel(part)).collect();
+        return if parts.len() == 3 && channels.len() == 3 {
+            Some(ColorRGB {
+                red: channels[0],
+                green: channels[1],
+                blue: channels[2],
+            })
+        } else {
+            None
+        };
+    }
+
+    None
+}
+
+/// Scale 1 to 4 hex digits to a byte, so `f` and `ffff` both mean 255.
+fn scale_channel(digits: &str) -> Option<u8> {
+    if digits.is_empty() || digits.len() > 4 || !digits.bytes().all(|b| (b as char).is_digit(16)) {
+        return None;
+    }
+    let max = (1u32 << (4 * digits.len())) - 1;
+    u32::from_str_radix(digits, 16).ok().map(|val| (val * 255 / max) as u8)
+}
+
+impl VTerm {
+    /// All 256 indexed colors along with the default colors.
+    pub fn palette(&self) -> Palette {
+        let (default_fg, default_bg) = self.state_get_default_colors();
+        Palette {
+            colors: (0..256).map(|i| self.state_get_rgb_color_from_palette(i)).collect(),
+            default_fg: default_fg,
+            default_bg: default_bg,
+        }
+    }
+
+    /// Use the palette for text written from now on. libvterm works out colors 16 to 255 itself
+    /// from the 6x6x6 cube and grey ramp, so only the first 16 are taken from the palette.
+    pub fn set_palette(&mut self, palette: &Palette) {
+        for (i, color) in palette.colors.iter().enumerate().take(16) {
+            let color = ffi::VTermColor {
+                red: color.red,
+                green: color.green,
+                blue: color.blue,
+            };
+            unsafe {
+                ffi::vterm_state_set_palette_color(self.state_ptr.get_mut(), i as c_int, &color)
+            };
+        }
+        self.state_set_default_colors(&palette.default_fg, &palette.default_bg);
+        self.inner.palette_index = PaletteIndex::new(unsafe { self.state_ptr.get() });
+    }
+}
+
+mod tests {
+    #![allow(unused_imports)]
+    #![allow(dead_code)]
+    use super::*;
+
+    fn rgb(red: u8, green: u8, blue: u8) -> ColorRGB {
+        ColorRGB {
+            red: red,
+            green: green,
+            blue: blue,
+        }
+    }
+
+    fn blank_palette() -> Palette {
+        Palette {
+            colors: vec![ColorRGB::default(); 16],
+            default_fg: ColorRGB::default(),
+            default_bg: ColorRGB::default(),
+        }
+    }
+
+    #[test]
+    fn palette_parses_x_colors() {
+        assert_eq!(parse_x_color("#cc241d"), Some(rgb(0xcc, 0x24, 0x1d)));
+        assert_eq!(parse_x_color("#f80"), Some(rgb(0xff, 0x88, 0x00)));
+        assert_eq!(parse_x_color("rgb:ff/8/0000"), Some(rgb(0xff, 0x88, 0x00)));
+        assert_eq!(parse_x_color("rgb:ff/88"), None);
+        assert_eq!(parse_x_color("#ggg"), None);
+        assert_eq!(parse_x_color("#aé123"), None);
+        assert_eq!(parse_x_color("#é"), None);
+        assert_eq!(parse_x_color("rgb:é/0/0"), None);
+        assert_eq!(parse_x_color("rgb:+f/0/0"), None);
+        assert_eq!(parse_x_color("red"), None);
+    }
+
+    #[test]
+    fn palette_loads_xresources() {
+        let mut palette = blank_palette();
+        palette.load_xresources("! gruvbox\n\
+                                 #define red #cc241d\n\
+                                 *.foreground:   #ebdbb2\n\
+                                 URxvt*background: rgb:28/28/28\n\
+                                 *.color1: red\n\
+                                 *color15: #fff\n\
+                                 *.color200: #123456\n\
+                                 *.cursorColor: #ebdbb2\n")
+            .unwrap();
+
+        assert_eq!(palette.default_fg, rgb(0xeb, 0xdb, 0xb2));
+        assert_eq!(palette.default_bg, rgb(0x28, 0x28, 0x28));
+        assert_eq!(palette.colors[1], rgb(0xcc, 0x24, 0x1d));
+        assert_eq!(palette.colors[15], rgb(0xff, 0xff, 0xff));
+        assert_eq!(palette.colors[0], ColorRGB::default());
+
+        let err = palette.load_xresources("*.color2: #zzzzzz").unwrap_err();
+        assert_eq!(err, Error::InvalidTheme("line 1: bad color \"#zzzzzz\"".to_string()));
+        let err = palette.load_xresources("*.color1: #aé123").unwrap_err();
+        assert_eq!(err, Error::InvalidTheme("line 1: bad color \"#aé123\"".to_string()));
+    }
+
+    #[test]
+    fn palette_loads_base16_yaml() {
+        let mut yaml = "scheme: \"Test\"\nauthor: \"Someone\"\n".to_string();
+        for i in 0..16 {
+            yaml.push_str(&format!("base{:02X}: \"{:02x}0000\" # comment\n", i, i));
+        }
+
+        let mut palette = blank_palette();
+        palette.load_base16_yaml(&yaml).unwrap();
+        assert_eq!(palette.default_bg, rgb(0x00, 0, 0));
+        assert_eq!(palette.default_fg, rgb(0x05, 0, 0));
+        assert_eq!(palette.colors[1], rgb(0x08, 0, 0));
+        assert_eq!(palette.colors[8], rgb(0x03, 0, 0));
+        assert_eq!(palette.colors[15], rgb(0x07, 0, 0));
+
+        let err = palette.load_base16_yaml("base00: \"000000\"").unwrap_err();
+        assert_eq!(err, Error::InvalidTheme("base01 is missing".to_string()));
+
+        let err = palette.load_base16_yaml("base00: \"aé123\"").unwrap_err();
+        assert_eq!(err, Error::InvalidTheme("line 1: bad color \"aé123\"".to_string()));
+    }
+}
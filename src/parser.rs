@@ -0,0 +1,116 @@
+use libc::c_void;
+use std::sync::mpsc;
+
+use super::*;
+
+/// Raw sequences as libvterm's parser splits them up, before the state layer interprets them.
+/// Byte strings are given as they were received.
+#[derive(PartialEq, Debug, Clone)]
+pub enum ParserEvent {
+    /// A run of printable bytes, which may end part way through a utf8 sequence
+    Text(Vec<u8>),
+    /// A C0 or C1 control byte
+    Control(u8),
+    /// The bytes after ESC, including the final byte
+    Escape(Vec<u8>),
+    Csi {
+        /// Private marker bytes such as `?` or `>`
+        leader: Vec<u8>,
+        args: Vec<CsiArg>,
+        intermed: Vec<u8>,
+        command: u8,
+    },
+    /// The contents of an OSC string, without the terminator
+    Osc(Vec<u8>),
+    /// The contents of a DCS string, without the terminator
+    Dcs(Vec<u8>),
+}
+
+/// One CSI argument. Subparameters separated by `:` are given as separate arguments, with
+/// `has_more` set on all but the last one. `CSI 1;2:3:4;5a` is five args with `has_more` set on 2
+/// and 3.
+#[derive(PartialEq, Debug, Clone)]
+pub struct CsiArg {
+    /// None if the argument was left out, like the first one in `CSI ;5H`
+    pub value: Option<u32>,
+    /// Whether a subparameter follows
+    pub has_more: bool,
+}
+
+impl CsiArg {
+    pub fn new(value: Option<u32>, has_more: bool) -> CsiArg {
+        CsiArg {
+            value: value,
+            has_more: has_more,
+        }
+    }
+
+    /// Decode an argument as libvterm gives it to the csi callback
+    pub fn from_raw(raw: ::libc::c_long) -> CsiArg {
+        let raw = raw as u32;
+        let value = raw & ffi::CSI_ARG_MASK;
+        CsiArg {
+            value: if value == ffi::CSI_ARG_MISSING {
+                None
+            } else {
+                Some(value)
+            },
+            has_more: raw & ffi::CSI_ARG_FLAG_MORE != 0,
+        }
+    }
+
+    /// The value, or the given default if the argument was left out
+    pub fn value_or(&self, default: u32) -> u32 {
+        self.value.unwrap_or(default)
+    }
+}
+
+impl VTerm {
+    /// Setup the vterm to generate ParserEvent messages to a channel, whose receiver end is in the
+    /// parser_event_rx field.
+    ///
+    /// libvterm only has one set of parser callbacks and the state layer is built on them, so
+    /// this takes over from it: the state and screen stop updating and their events stop. It's
+    /// meant for looking at what an application sends rather than for emulating a terminal.
+    pub fn parser_receive_events(&mut self) {
+        let mut callbacks: ffi::VTermParserCallbacks = Default::default();
+        callbacks.text = Some(::parser_callbacks::text);
+        callbacks.control = Some(::parser_callbacks::control);
+        callbacks.escape = Some(::parser_callbacks::escape);
+        callbacks.csi = Some(::parser_callbacks::csi);
+        callbacks.osc = Some(::parser_callbacks::osc);
+        callbacks.dcs = Some(::parser_callbacks::dcs);
+
+        self.inner.parser_callbacks = Some(callbacks);
+
+        if self.inner.parser_event_tx.is_none() {
+            let (tx, rx) = mpsc::channel();
+            self.inner.parser_event_tx = Some(tx);
+            self.parser_event_rx = Some(rx);
+        }
+
+        unsafe {
+            let inner_ptr: *mut c_void = &mut *self.inner as *mut _ as *mut c_void;
+            ffi::vterm_parser_set_callbacks(self.ptr.get_mut(),
+                                            self.inner.parser_callbacks.as_ref().unwrap(),
+                                            inner_ptr);
+        }
+    }
+}
+
+mod tests {
+    #![allow(unused_imports)]
+    use super::super::*;
+
+    #[test]
+    fn csi_arg_decodes_missing_and_more() {
+        assert_eq!(CsiArg::from_raw(5), CsiArg::new(Some(5), false));
+        assert_eq!(CsiArg::from_raw(ffi::CSI_ARG_MISSING as ::libc::c_long),
+                   CsiArg::new(None, false));
+        assert_eq!(CsiArg::from_raw((ffi::CSI_ARG_FLAG_MORE | 2) as i32 as ::libc::c_long),
+                   CsiArg::new(Some(2), true));
+        assert_eq!(CsiArg::from_raw((ffi::CSI_ARG_FLAG_MORE | ffi::CSI_ARG_MISSING) as i32 as
+                                    ::libc::c_long),
+                   CsiArg::new(None, true));
+    }
+}
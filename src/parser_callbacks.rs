@@ -0,0 +1,111 @@
+#![allow(unused_variables)]
+
+use libc::{c_char, c_int, c_long, c_uchar, c_void, size_t};
+use std::ffi::CStr;
+use std::slice;
+use std::sync::mpsc::Sender;
+
+use super::*;
+use inner::{Inner, with_inner};
+
+/// libvterm hands over everything left in the input, so this has to say how much of it is text.
+/// That's up to the first byte the parser treats as a control.
+pub extern "C" fn text(bytes: *const c_char, len: size_t, inner: *mut c_void) -> c_int {
+    with_sender(inner, |inner, tx| {
+        let bytes = unsafe { bytes_from_ptr(bytes, len) };
+        let is_utf8 = unsafe { int_to_bool(ffi::vterm_get_utf8(inner.vterm_ptr)) };
+
+        let eaten = bytes.iter()
+            .position(|&b| b < 0x20 || b == 0x7f || (!is_utf8 && b >= 0x80 && b < 0xa0))
+            .unwrap_or(bytes.len());
+        if eaten == 0 {
+            return 0;
+        }
+
+        match tx.send(ParserEvent::Text(bytes[..eaten].to_vec())) {
+            Ok(_) => eaten as c_int,
+            Err(_) => 0,
+        }
+    })
+}
+
+pub extern "C" fn control(control: c_uchar, inner: *mut c_void) -> c_int {
+    with_sender(inner, |inner, tx| send(tx, ParserEvent::Control(control as u8)))
+}
+
+pub extern "C" fn escape(bytes: *const c_char, len: size_t, inner: *mut c_void) -> c_int {
+    with_sender(inner, |inner, tx| {
+        let bytes = unsafe { bytes_from_ptr(bytes, len) };
+        send(tx, ParserEvent::Escape(bytes.to_vec()))
+    })
+}
+
+pub extern "C" fn csi(leader: *const c_char,
+                      args: *const c_long,
+                      argcount: c_int,
+                      intermed: *const c_char,
+                      command: c_char,
+                      inner: *mut c_void)
+                      -> c_int {
+    with_sender(inner, |inner, tx| {
+        let args = unsafe { slice::from_raw_parts(args, argcount as usize) };
+        let event = ParserEvent::Csi {
+            leader: unsafe { bytes_from_c_str(leader) },
+            args: args.iter().map(|&arg| CsiArg::from_raw(arg)).collect(),
+            intermed: unsafe { bytes_from_c_str(intermed) },
+            command: command as u8,
+        };
+        send(tx, event)
+    })
+}
+
+pub extern "C" fn osc(command: *const c_char, cmdlen: size_t, inner: *mut c_void) -> c_int {
+    with_sender(inner, |inner, tx| {
+        let bytes = unsafe { bytes_from_ptr(command, cmdlen) };
+        send(tx, ParserEvent::Osc(bytes.to_vec()))
+    })
+}
+
+pub extern "C" fn dcs(command: *const c_char, cmdlen: size_t, inner: *mut c_void) -> c_int {
+    with_sender(inner, |inner, tx| {
+        let bytes = unsafe { bytes_from_ptr(command, cmdlen) };
+        send(tx, ParserEvent::Dcs(bytes.to_vec()))
+    })
+}
+
+fn send(tx: &Sender<ParserEvent>, event: ParserEvent) -> c_int {
+    match tx.send(event) {
+        Ok(_) => 1,
+        Err(_) => 0,
+    }
+}
+
+pub unsafe fn bytes_from_ptr<'a>(ptr: *const c_char, len: size_t) -> &'a [u8] {
+    if ptr.is_null() {
+        &[]
+    } else {
+        slice::from_raw_parts(ptr as *const u8, len as usize)
+    }
+}
+
+/// libvterm passes NULL rather than an empty string when there are no leader or intermediate
+/// bytes.
+pub unsafe fn bytes_from_c_str(ptr: *const c_char) -> Vec<u8> {
+    if ptr.is_null() {
+        vec![]
+    } else {
+        CStr::from_ptr(ptr).to_bytes().to_vec()
+    }
+}
+
+/// Call the given closure with the vterms sender, if it exists. Panics are caught by with_inner.
+fn with_sender<F>(inner: *mut c_void, closure: F) -> c_int
+    where F: FnOnce(&Inner, &Sender<ParserEvent>) -> c_int
+{
+    with_inner(inner, |inner| {
+        match inner.parser_event_tx.as_ref() {
+            Some(tx) => closure(inner, tx),
+            None => 0,
+        }
+    })
+}
@@ -0,0 +1,59 @@
+use super::*;
+
+/// The attributes new glyphs are written with, as set by SGR.
+#[derive(PartialEq, Debug, Clone)]
+pub struct Pen {
+    pub bold: bool,
+    pub underline: Underline,
+    pub italic: bool,
+    pub blink: bool,
+    pub reverse: bool,
+    pub strike: bool,
+    pub font: i32,
+    pub fg_rgb: ColorRGB,
+    /// None if the color isn't in the palette
+    pub fg_palette: Option<ColorPalette>,
+    pub bg_rgb: ColorRGB,
+    /// None if the color isn't in the palette
+    pub bg_palette: Option<ColorPalette>,
+}
+
+impl VTerm {
+    /// The current pen, for when events about it weren't being listened for.
+    pub fn pen(&self) -> Pen {
+        let state_ptr = unsafe { self.state_ptr.get() };
+
+        let boolean = |attr| unsafe {
+            int_to_bool(ffi::vterm_state_get_penattr_boolean(state_ptr, attr))
+        };
+        let number = |attr| unsafe { ffi::vterm_state_get_penattr_number(state_ptr, attr) };
+        let color = |attr| unsafe {
+            ffi::vterm_state_get_penattr_color(state_ptr, attr).as_color_rgb()
+        };
+
+        let underline = number(ffi::VTermAttr::Underline);
+        let underline = match Underline::try_from_i32(underline) {
+            Ok(underline) => underline,
+            Err(e) => {
+                warn!("{}", e);
+                Underline::None
+            }
+        };
+        let fg_rgb = color(ffi::VTermAttr::Foreground);
+        let bg_rgb = color(ffi::VTermAttr::Background);
+
+        Pen {
+            bold: boolean(ffi::VTermAttr::Bold),
+            underline: underline,
+            italic: boolean(ffi::VTermAttr::Italic),
+            blink: boolean(ffi::VTermAttr::Blink),
+            reverse: boolean(ffi::VTermAttr::Reverse),
+            strike: boolean(ffi::VTermAttr::Strike),
+            font: number(ffi::VTermAttr::Font),
+            fg_palette: self.state_get_palette_color_from_rgb(&fg_rgb),
+            fg_rgb: fg_rgb,
+            bg_palette: self.state_get_palette_color_from_rgb(&bg_rgb),
+            bg_rgb: bg_rgb,
+        }
+    }
+}
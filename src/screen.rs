@@ -1,4 +1,4 @@
-use libc::{c_void, size_t, c_char};
+use libc::{size_t, c_char};
 use std::sync::mpsc;
 
 use super::*;
@@ -7,6 +7,11 @@ use super::*;
 pub enum ScreenEvent {
     AltScreen(AltScreenEvent),
     Bell,
+    /// An application set the clipboard with OSC 52
+    ClipboardSet { selection: String, data: Vec<u8> },
+    /// An application asked for the clipboard contents with OSC 52. Answer with
+    /// `VTerm::clipboard_reply`.
+    ClipboardQuery { selection: String },
     CursorBlink(CursorBlinkEvent),
     CursorShape(CursorShapeEvent),
     CursorVisible(CursorVisibleEvent),
@@ -15,14 +20,18 @@ pub enum ScreenEvent {
     Mouse(MouseEvent),
     MoveCursor(MoveCursorEvent),
     MoveRect(MoveRectEvent),
+    Notification(NotificationEvent),
     Resize(ResizeEvent),
     Reverse(ReverseEvent),
     SbPopLine(SbPopLineEvent),
     SbPushLine(SbPushLineEvent),
+    /// A shell integration mark was set with OSC 133 while the cursor was on the row
+    ShellMark { mark: ShellMark, row: usize },
     Title(TitleEvent),
+    WorkingDirectory(WorkingDirectoryEvent),
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct ScreenCallbacksConfig {
     pub damage: bool,
     pub move_rect: bool,
@@ -32,6 +41,14 @@ pub struct ScreenCallbacksConfig {
     pub resize: bool,
     pub sb_pushline: bool,
     pub sb_popline: bool,
+    /// OSC 52 clipboard sets and queries, if the clipboard policy allows them
+    pub clipboard: bool,
+    /// OSC 133 shell integration marks
+    pub shell_marks: bool,
+    /// OSC 7 current directory reports
+    pub working_directory: bool,
+    /// OSC 9 and OSC 777 desktop notifications
+    pub notifications: bool,
 }
 
 impl ScreenCallbacksConfig {
@@ -45,6 +62,10 @@ impl ScreenCallbacksConfig {
             resize: true,
             sb_pushline: true,
             sb_popline: true,
+            clipboard: true,
+            shell_marks: true,
+            working_directory: true,
+            notifications: true,
         }
     }
 
@@ -58,6 +79,10 @@ impl ScreenCallbacksConfig {
             resize: false,
             sb_pushline: false,
             sb_popline: false,
+            clipboard: false,
+            shell_marks: false,
+            working_directory: false,
+            notifications: false,
         }
     }
 }
@@ -75,13 +100,22 @@ impl VTerm {
         unsafe { ffi::vterm_screen_reset(self.screen_ptr.get_mut(), super::bool_to_int(is_hard)) }
     }
 
-    /// Return the cell at the given position
+    /// Return the cell at the given position. Panics if the position is out of bounds.
     pub fn screen_get_cell(&self, pos: &Pos) -> ScreenCell {
+        match self.try_screen_get_cell(pos) {
+            Ok(cell) => cell,
+            Err(e) => panic!("{}", e),
+        }
+    }
+
+    /// Return the cell at the given position or an error if the position is out of bounds.
+    pub fn try_screen_get_cell(&self, pos: &Pos) -> Result<ScreenCell, Error> {
         let size = self.get_size();
         if pos.x >= size.width || pos.y >= size.height {
-            panic!("given position out of bounds: size={:?} pos={:?}",
-                   size,
-                   pos);
+            return Err(Error::PosOutOfBounds {
+                pos: pos.clone(),
+                size: size,
+            });
         }
 
         let cell_buf = unsafe { ffi::vterm_cell_new() };
@@ -90,32 +124,62 @@ impl VTerm {
                                        ffi::VTermPos::from_pos(&pos),
                                        cell_buf)
         };
-        let cell = ScreenCell::from_ptr(cell_buf, &self); // shouldn't this take &cell_buf?
+        let mut cell = ScreenCell::from_ptr(cell_buf, &self.inner.palette_index);
         unsafe { ffi::vterm_cell_free(cell_buf) };
 
-        cell
+        // Erased cells keep their old link in the tracker, but a blank cell has no link
+        if !cell.chars.is_empty() || cell.is_wide_continuation() {
+            cell.link = self.inner.tracker.link(pos);
+        }
+        cell.protected = int_to_bool(unsafe {
+            ffi::vterm_screen_get_cell_protected(self.screen_ptr.get(),
+                                                 ffi::VTermPos::from_pos(&pos))
+        });
+
+        Ok(cell)
     }
 
     // Returns the text within the rect as a String. Invalid utf8 sequences are replaces with or
     // panics if invalid utf8 bytes are found
     pub fn screen_get_text_lossy(&self, rect: &Rect) -> String {
-        let bytes = self.get_text_as_bytes(rect);
-        String::from_utf8_lossy(&bytes).into_owned()
+        match self.try_screen_get_text_lossy(rect) {
+            Ok(text) => text,
+            Err(e) => panic!("{}", e),
+        }
+    }
+
+    /// Returns the text within the rect as a String, replacing invalid utf8 sequences, or an error
+    /// if the rect is out of bounds.
+    pub fn try_screen_get_text_lossy(&self, rect: &Rect) -> Result<String, Error> {
+        let bytes = try!(self.get_text_as_bytes(rect));
+        Ok(String::from_utf8_lossy(&bytes).into_owned())
     }
 
     // Returns the text within the rect as a String or panics if invalid utf8 bytes are found
     pub fn screen_get_text(&self, rect: &Rect) -> Result<String, ::std::string::FromUtf8Error> {
-        let bytes = self.get_text_as_bytes(rect);
+        let bytes = match self.get_text_as_bytes(rect) {
+            Ok(bytes) => bytes,
+            Err(e) => panic!("{}", e),
+        };
         let v = try! { String::from_utf8(bytes) };
         Ok(v)
     }
 
-    fn get_text_as_bytes(&self, rect: &Rect) -> Vec<u8> {
+    /// Returns the text within the rect as a String or an error if the rect is out of bounds or
+    /// the text isn't valid utf8.
+    pub fn try_screen_get_text(&self, rect: &Rect) -> Result<String, Error> {
+        let bytes = try!(self.get_text_as_bytes(rect));
+        let v = try!(String::from_utf8(bytes));
+        Ok(v)
+    }
+
+    fn get_text_as_bytes(&self, rect: &Rect) -> Result<Vec<u8>, Error> {
         let screen_rect = Rect::new(Pos::new(0, 0), self.get_size());
         if !screen_rect.contains_rect(&rect) {
-            panic!("given rect out of bounds: size={:?} rect={:?}",
-                   self.get_size(),
-                   rect);
+            return Err(Error::RectOutOfBounds {
+                rect: rect.clone(),
+                size: self.get_size(),
+            });
         }
 
         let size: usize = rect.size.width * rect.size.height * ffi::VTERM_MAX_CHARS_PER_CELL;
@@ -131,7 +195,19 @@ impl VTerm {
             bytes.set_len(len);
         }
 
-        bytes.into_iter().map(|c| c as u8).collect()
+        Ok(bytes.into_iter().map(|c| c as u8).collect())
+    }
+
+    /// Give the screen an alternate buffer, which applications switch to with DEC modes 47, 1047
+    /// and 1049. Without it those modes keep using the normal buffer.
+    pub fn screen_enable_altscreen(&mut self, is_enabled: bool) {
+        unsafe {
+            ffi::vterm_screen_enable_altscreen(self.screen_ptr.get_mut(),
+                                               super::bool_to_int(is_enabled))
+        };
+        if is_enabled {
+            self.inner.tracker.has_altscreen = true;
+        }
     }
 
     pub fn screen_flush_damage(&mut self) {
@@ -148,77 +224,62 @@ impl VTerm {
         unsafe { ffi::vterm_screen_set_damage_merge(self.screen_ptr.get_mut(), ffi_size) };
     }
 
+    /// Returns the cells in the rect in row order. Panics if the rect is out of bounds.
     pub fn screen_get_cells_in_rect(&self, rect: &Rect) -> Vec<ScreenCell> {
+        match self.try_screen_get_cells_in_rect(rect) {
+            Ok(cells) => cells,
+            Err(e) => panic!("{}", e),
+        }
+    }
+
+    /// Returns the cells in the rect in row order or an error if the rect is out of bounds.
+    pub fn try_screen_get_cells_in_rect(&self, rect: &Rect) -> Result<Vec<ScreenCell>, Error> {
+        let screen_rect = Rect::new(Pos::new(0, 0), self.get_size());
+        if !screen_rect.contains_rect(&rect) {
+            return Err(Error::RectOutOfBounds {
+                rect: rect.clone(),
+                size: self.get_size(),
+            });
+        }
+
         let mut cells: Vec<ScreenCell> = Vec::new(); // capacity is known here FYI
 
         for pos in rect.positions() {
-            cells.push(self.screen_get_cell(&pos));
+            cells.push(try!(self.try_screen_get_cell(&pos)));
         }
 
-        cells
+        Ok(cells)
     }
 
     /// calling this method will setup the vterm to generate ScreenEvent messages to a channel. The
     /// returned result indicates whether the channel was already created. The receiver end of the
     /// channel can be had by accessing the screen_events_rx field.
+    ///
+    /// This replaces any handler given to set_screen_handler.
     pub fn screen_receive_events(&mut self, config: &ScreenCallbacksConfig) {
-        let mut callbacks: ffi::VTermScreenCallbacks = Default::default();
-
-        callbacks.damage = if config.damage {
-            Some(::screen_callbacks::damage)
-        } else {
-            None
-        };
-        callbacks.move_rect = if config.move_rect {
-            Some(::screen_callbacks::move_rect)
-        } else {
-            None
-        };
-        callbacks.move_cursor = if config.move_cursor {
-            Some(::screen_callbacks::move_cursor)
-        } else {
-            None
-        };
-        callbacks.set_term_prop = if config.set_term_prop {
-            Some(::screen_callbacks::set_term_prop)
-        } else {
-            None
-        };
-        callbacks.bell = if config.bell {
-            Some(::screen_callbacks::bell)
-        } else {
-            None
-        };
-        callbacks.resize = if config.resize {
-            Some(::screen_callbacks::resize)
-        } else {
-            None
-        };
-        callbacks.sb_pushline = if config.sb_pushline {
-            Some(::screen_callbacks::sb_pushline)
-        } else {
-            None
-        };
-        callbacks.sb_popline = if config.sb_popline {
-            Some(::screen_callbacks::sb_popline)
-        } else {
-            None
-        };
-
-        self.screen_callbacks = Some(callbacks);
-
         if self.screen_event_tx.is_none() {
             let (tx, rx) = mpsc::channel();
             self.screen_event_tx = Some(tx);
             self.screen_event_rx = Some(rx);
         }
 
-        unsafe {
-            let self_ptr: *mut c_void = self as *mut _ as *mut c_void;
-            ffi::vterm_screen_set_callbacks(self.screen_ptr.get_mut(),
-                                            self.screen_callbacks.as_ref().unwrap(),
-                                            self_ptr);
-        }
+        let handler = ChannelScreenHandler::new(self.screen_event_tx.as_ref().unwrap().clone());
+        self.register_screen_handler(Box::new(handler), config);
+    }
+
+    /// Call the handler's methods synchronously for every screen callback, while input is being
+    /// written. This replaces the channel set up by screen_receive_events.
+    pub fn set_screen_handler(&mut self, handler: Box<ScreenHandler>) {
+        self.register_screen_handler(handler, &ScreenCallbacksConfig::all());
+    }
+
+    fn register_screen_handler(&mut self,
+                               handler: Box<ScreenHandler>,
+                               config: &ScreenCallbacksConfig) {
+        self.inner.screen_handler = Some(handler);
+        ::screen_callbacks::set_callbacks(unsafe { self.screen_ptr.get_mut() },
+                                          &mut self.inner,
+                                          config);
     }
 }
 
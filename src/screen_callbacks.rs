@@ -1,168 +1,201 @@
+#![allow(unused_variables)]
+
 use libc::{c_int, c_void};
 
 use super::*;
+use inner::{Inner, with_inner};
 
-pub extern "C" fn damage(rect: ffi::VTermRect, vterm: *mut c_void) -> c_int {
-    let vterm: &mut VTerm = unsafe { &mut *(vterm as *mut VTerm) };
-    match vterm.screen_event_tx.as_ref() {
-        Some(tx) => {
-            match tx.send(ScreenEvent::Damage(DamageEvent { rect: rect.as_rect() })) {
-                Ok(_) => 1,
-                Err(_) => 0,
-            }
-        }
-        None => 0,
-    }
+pub extern "C" fn damage(rect: ffi::VTermRect, inner: *mut c_void) -> c_int {
+    with_handler(inner, |state_ptr, handler| handler.damage(rect.as_rect()))
 }
 
 pub extern "C" fn move_rect(dest: ffi::VTermRect,
                             src: ffi::VTermRect,
-                            vterm: *mut c_void)
+                            inner: *mut c_void)
                             -> c_int {
-    let vterm: &mut VTerm = unsafe { &mut *(vterm as *mut VTerm) };
-    match vterm.screen_event_tx.as_ref() {
-        Some(tx) => {
-            match tx.send(ScreenEvent::MoveRect(MoveRectEvent {
-                dest: dest.as_rect(),
-                src: src.as_rect(),
-            })) {
-                Ok(_) => 1,
-                Err(_) => 0,
-            }
-        }
-        None => 0,
-    }
+    with_handler(inner,
+                 |state_ptr, handler| handler.move_rect(dest.as_rect(), src.as_rect()))
 }
 
 pub extern "C" fn move_cursor(new: ffi::VTermPos,
                               old: ffi::VTermPos,
                               is_visible: c_int,
-                              vterm: *mut c_void)
+                              inner: *mut c_void)
                               -> c_int {
-    let vterm: &mut VTerm = unsafe { &mut *(vterm as *mut VTerm) };
-    match vterm.screen_event_tx.as_ref() {
-        Some(tx) => {
-            let event = ScreenEvent::MoveCursor(MoveCursorEvent {
-                new: new.as_pos(),
-                old: old.as_pos(),
-                is_visible: super::int_to_bool(is_visible),
-            });
-            match tx.send(event) {
-                Ok(_) => 1,
-                Err(_) => 0,
-            }
-        }
-        None => 0,
-    }
+    with_handler(inner, |state_ptr, handler| {
+        handler.move_cursor(new.as_pos(), old.as_pos(), int_to_bool(is_visible))
+    })
 }
 
 pub extern "C" fn set_term_prop(prop: ffi::VTermProp,
                                 val: *mut ffi::VTermValue,
-                                vterm: *mut c_void)
+                                inner: *mut c_void)
                                 -> c_int {
-    let event: ScreenEvent = match prop {
-        ffi::VTermProp::VTermPropAltscreen => {
-            let val = unsafe { int_to_bool(ffi::vterm_value_get_boolean(val)).clone() };
-            ScreenEvent::AltScreen(AltScreenEvent { is_on: val })
-        }
-        ffi::VTermProp::VTermPropCursorBlink => {
-            let val = unsafe { int_to_bool(ffi::vterm_value_get_boolean(val)).clone() };
-            ScreenEvent::CursorBlink(CursorBlinkEvent { is_on: val })
-        }
-        ffi::VTermProp::VTermPropCursorShape => {
-            ScreenEvent::CursorShape(CursorShapeEvent { shape: CursorShape::Block })
-        }
-        ffi::VTermProp::VTermPropCursorVisible => {
-            ScreenEvent::CursorVisible(CursorVisibleEvent { is_on: true })
-        }
-        ffi::VTermProp::VTermPropIconName => {
-            ScreenEvent::IconName(IconNameEvent { name: "fake icon name".to_string() })
+    with_handler(inner, |state_ptr, handler| {
+        match ::state_callbacks::term_prop_from_ptr(prop, val) {
+            Some(prop) => handler.set_term_prop(prop),
+            None => true,
         }
-        ffi::VTermProp::VTermPropMouse => ScreenEvent::Mouse(MouseEvent { mode: MouseMode::None }),
-        ffi::VTermProp::VTermPropReverse => ScreenEvent::Reverse(ReverseEvent { is_on: true }),
-        ffi::VTermProp::VTermPropTitle => {
-            ScreenEvent::Title(TitleEvent { title: "fake title".to_string() })
-        }
-    };
-
-    let vterm: &mut VTerm = unsafe { &mut *(vterm as *mut VTerm) };
-    match vterm.screen_event_tx.as_ref() {
-        Some(tx) => {
-            match tx.send(event) {
-                Ok(_) => 1,
-                Err(_) => 0,
-            }
-        }
-        None => 0,
-    }
+    })
 }
 
-pub extern "C" fn bell(vterm: *mut c_void) -> c_int {
-    let vterm: &mut VTerm = unsafe { &mut *(vterm as *mut VTerm) };
-    match vterm.screen_event_tx.as_ref() {
-        Some(tx) => {
-            match tx.send(ScreenEvent::Bell) {
-                Ok(_) => 1,
-                Err(_) => 0,
-            }
-        }
-        None => 0,
-    }
+pub extern "C" fn bell(inner: *mut c_void) -> c_int {
+    with_handler(inner, |state_ptr, handler| handler.bell())
 }
-pub extern "C" fn resize(rows: c_int, cols: c_int, vterm: *mut c_void) -> c_int {
-    let vterm: &mut VTerm = unsafe { &mut *(vterm as *mut VTerm) };
-    match vterm.screen_event_tx.as_ref() {
-        Some(tx) => {
-            match tx.send(ScreenEvent::Resize(ResizeEvent {
-                size: Size::new(cols as usize, rows as usize),
-            })) {
-                Ok(_) => 1,
-                Err(_) => 0,
-            }
-        }
-        None => 0,
-    }
+
+pub extern "C" fn resize(rows: c_int, cols: c_int, inner: *mut c_void) -> c_int {
+    with_handler(inner, |state_ptr, handler| {
+        handler.resize(Size::new(cols as usize, rows as usize))
+    })
 }
+
 pub extern "C" fn sb_pushline(cols: c_int,
                               cells_ptr: *const ffi::VTermScreenCell,
-                              vterm: *mut c_void)
+                              inner: *mut c_void)
                               -> c_int {
-    let vterm: &mut VTerm = unsafe { &mut *(vterm as *mut VTerm) };
-    match vterm.screen_event_tx.as_ref() {
-        Some(tx) => {
-            let mut cells = vec![];
-            for i in 0..(cols as usize) {
-                let ptr = unsafe { ffi::vterm_cell_pointer_arithmetic(cells_ptr, i as c_int) };
-                cells.push(ScreenCell::from_ptr(ptr, &vterm));
+    with_inner(inner, |inner| {
+        let mut cells = cells_from_ptr(cols, cells_ptr, &inner.palette_index);
+        let row = inner.tracker.next_pushed_row();
+        for (cell, &link) in cells.iter_mut().zip(row.links.iter()) {
+            if !cell.chars.is_empty() || cell.is_wide_continuation() {
+                cell.link = link;
             }
+        }
 
-            match tx.send(ScreenEvent::SbPushLine(SbPushLineEvent { cells: cells })) {
-                Ok(_) => 1,
-                Err(_) => 0,
+        if inner.screen_callbacks_config.sb_pushline {
+            if let Some(handler) = inner.screen_handler.as_mut() {
+                handler.sb_pushline(&cells);
             }
         }
-        None => 0,
-    }
+
+        inner.scrollback.push_with_marks(cells, row.marks);
+        1
+    })
 }
 
+/// libvterm asks for a line back when the screen gets taller. The most recent scrollback line is
+/// written into the cells it gives us, padded with blank cells if the screen is now wider.
 pub extern "C" fn sb_popline(cols: c_int,
                              cells_ptr: *const ffi::VTermScreenCell,
-                             vterm: *mut c_void)
+                             inner: *mut c_void)
                              -> c_int {
-    let vterm: &mut VTerm = unsafe { &mut *(vterm as *mut VTerm) };
-    match vterm.screen_event_tx.as_ref() {
-        Some(tx) => {
-            let mut cells = vec![];
-            for i in 0..(cols as usize) {
-                let ptr = unsafe { ffi::vterm_cell_pointer_arithmetic(cells_ptr, i as c_int) };
-                cells.push(ScreenCell::from_ptr(ptr, &vterm));
+    with_inner(inner, |inner| {
+        let (line, marks) = match inner.scrollback.pop_with_marks() {
+            Some(line) => line,
+            None => return 0,
+        };
+
+        let mut blank: ScreenCell = Default::default();
+        let mut fg: ffi::VTermColor = Default::default();
+        let mut bg: ffi::VTermColor = Default::default();
+        unsafe { ffi::vterm_state_get_default_colors(inner.state_ptr, &mut fg, &mut bg) };
+        blank.fg_rgb = fg.as_color_rgb();
+        blank.bg_rgb = bg.as_color_rgb();
+
+        for i in 0..(cols as usize) {
+            let ptr = unsafe { ffi::vterm_cell_pointer_arithmetic(cells_ptr, i as c_int) };
+            let mut cell = line.get(i).unwrap_or(&blank);
+            // libvterm marks the cell after a wide character, which isn't there in the last column
+            if i == cols as usize - 1 && cell.width == 2 {
+                cell = &blank;
+            }
+            cell.write_to_ptr(ptr as *mut ffi::VTermScreenCell);
+
+            // libvterm steps through the line by cell width, so zero would hang it
+            if cell.width == 0 {
+                unsafe { ffi::vterm_cell_set_width(ptr as *mut ffi::VTermScreenCell, 1) };
             }
+        }
+
+        inner.tracker.popped_row(::tracker::Row {
+            links: line.iter().map(|cell| cell.link).collect(),
+            marks: marks,
+        });
 
-            match tx.send(ScreenEvent::SbPopLine(SbPopLineEvent { cells: cells })) {
-                Ok(_) => 1,
-                Err(_) => 0,
+        if inner.screen_callbacks_config.sb_popline {
+            if let Some(handler) = inner.screen_handler.as_mut() {
+                handler.sb_popline(&line);
             }
         }
-        None => 0,
+
+        1
+    })
+}
+
+fn cells_from_ptr(cols: c_int,
+                  cells_ptr: *const ffi::VTermScreenCell,
+                  palette_index: &PaletteIndex)
+                  -> Vec<ScreenCell> {
+    let mut cells = vec![];
+    for i in 0..(cols as usize) {
+        let ptr = unsafe { ffi::vterm_cell_pointer_arithmetic(cells_ptr, i as c_int) };
+        cells.push(ScreenCell::from_ptr(ptr, palette_index));
     }
+    cells
+}
+
+/// Give libvterm the screen callbacks for the given config, with the Inner as user data. The
+/// scrollback callbacks are always set since the scrollback store needs them, the config only
+/// decides whether the screen handler hears about them.
+pub fn set_callbacks(screen_ptr: *mut ffi::VTermScreen,
+                     inner: &mut Inner,
+                     config: &ScreenCallbacksConfig) {
+    let mut callbacks: ffi::VTermScreenCallbacks = Default::default();
+
+    callbacks.damage = if config.damage {
+        Some(damage)
+    } else {
+        None
+    };
+    callbacks.move_rect = if config.move_rect {
+        Some(move_rect)
+    } else {
+        None
+    };
+    callbacks.move_cursor = if config.move_cursor {
+        Some(move_cursor)
+    } else {
+        None
+    };
+    callbacks.set_term_prop = if config.set_term_prop {
+        Some(set_term_prop)
+    } else {
+        None
+    };
+    callbacks.bell = if config.bell {
+        Some(bell)
+    } else {
+        None
+    };
+    callbacks.resize = if config.resize {
+        Some(resize)
+    } else {
+        None
+    };
+    callbacks.sb_pushline = Some(sb_pushline);
+    callbacks.sb_popline = Some(sb_popline);
+
+    inner.screen_callbacks = Some(callbacks);
+    inner.screen_callbacks_config = config.clone();
+
+    unsafe {
+        let inner_ptr: *mut c_void = inner as *mut _ as *mut c_void;
+        ffi::vterm_screen_set_callbacks(screen_ptr,
+                                        inner.screen_callbacks.as_ref().unwrap(),
+                                        inner_ptr);
+    }
+}
+
+/// Call the given closure with the vterms screen handler, if there is one. Panics are caught by
+/// with_inner.
+fn with_handler<F>(inner: *mut c_void, closure: F) -> c_int
+    where F: FnOnce(*const ffi::VTermState, &mut ScreenHandler) -> bool
+{
+    with_inner(inner, |inner| {
+        let state_ptr = inner.state_ptr;
+        match inner.screen_handler.as_mut() {
+            Some(handler) => if closure(state_ptr, &mut **handler) { 1 } else { 0 },
+            None => 0,
+        }
+    })
 }
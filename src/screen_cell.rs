@@ -1,5 +1,5 @@
 use std::vec::Vec;
-use libc::{uint32_t, size_t};
+use libc::{uint32_t, size_t, c_char, c_uint};
 
 use super::*;
 
@@ -12,6 +12,9 @@ pub struct ColorRGB {
 
 pub type ColorPalette = usize;
 
+/// What libvterm puts in the cell after a wide character, (uint32_t)-1
+const WIDE_CONTINUATION: uint32_t = 0xffffffff;
+
 #[derive(Debug, Default, PartialEq, Clone)]
 pub struct ScreenCellAttr {
     pub bold: bool,
@@ -27,23 +30,35 @@ pub struct ScreenCellAttr {
 
 #[derive(Debug, PartialEq, Clone)]
 pub struct ScreenCell {
-    /// The bytes for the glyph in the cell
-    pub chars: Vec<u8>,
+    /// The character in the cell followed by any combining characters. Empty for an erased cell
+    /// and for the cell after a wide character.
+    pub chars: Vec<char>,
+    /// How many columns the character takes up. This is 2 for a wide character and 0 for the cell
+    /// after it, which the wide character covers.
     pub width: u8,
     pub attrs: ScreenCellAttr,
     /// foreground color in rgb
     pub fg_rgb: ColorRGB,
     /// background color in rgb
     pub bg_rgb: ColorRGB,
-    /// foreground color as a palette index
-    pub fg_palette: ColorPalette,
-    /// background color as a palette index rgb
-    pub bg_palette: ColorPalette,
+    /// foreground color as a palette index, or None if the color isn't in the palette
+    pub fg_palette: Option<ColorPalette>,
+    /// background color as a palette index, or None if the color isn't in the palette
+    pub bg_palette: Option<ColorPalette>,
+    /// The OSC 8 hyperlink the cell is part of. See `VTerm::hyperlink`.
+    pub link: Option<LinkId>,
+    /// Set by DECSCA, so selective erases leave the cell alone. Only known for cells on the
+    /// screen, scrollback cells never have it.
+    pub protected: bool,
 }
 
 impl ScreenCell {
-    // Copies data from the given pointer. Doesn't free the pointer or anything.
-    pub fn from_ptr(ptr: *const ffi::VTermScreenCell, vterm: &VTerm) -> ScreenCell {
+    // Copies data from the given pointer. Doesn't free the pointer or anything. The palette index
+    // is used to look up palette indexes for the colors. libvterm doesn't know about links, and
+    // VTermScreenCell doesn't have protection, so those are always unset here.
+    pub fn from_ptr(ptr: *const ffi::VTermScreenCell,
+                    palette_index: &PaletteIndex)
+                    -> ScreenCell {
         let fg_rgb = unsafe { ffi::vterm_cell_get_fg(ptr) };
         let bg_rgb = unsafe { ffi::vterm_cell_get_bg(ptr) };
 
@@ -53,14 +68,12 @@ impl ScreenCell {
                                       buf.as_mut_ptr(),
                                       ffi::VTERM_MAX_CHARS_PER_CELL as size_t)
         };
-        let buf: [u8; ffi::VTERM_MAX_CHARS_PER_CELL * 4] = unsafe { ::std::mem::transmute(buf) };
-        let mut chars: Vec<u8> = vec![];
-        chars.extend_from_slice(&buf[0..chars_count as usize * 4]);
+        let codes = &buf[0..chars_count as usize];
 
-        unsafe {
+        let mut cell = unsafe {
             ScreenCell {
-                chars: chars,
-                width: ffi::vterm_cell_get_width(ptr) as u8,
+                chars: vec![],
+                width: 0,
                 attrs: ScreenCellAttr {
                     bold: int_to_bool(ffi::vterm_cell_get_bold(ptr) as i32),
                     underline: ffi::vterm_cell_get_underline(ptr) as u8,
@@ -82,11 +95,101 @@ impl ScreenCell {
                     green: bg_rgb.green,
                     blue: bg_rgb.blue,
                 },
-                fg_palette: vterm.state_get_palette_color_from_c_rgb(&fg_rgb),
-                bg_palette: vterm.state_get_palette_color_from_c_rgb(&bg_rgb),
+                fg_palette: palette_index.get(&fg_rgb.as_color_rgb()),
+                bg_palette: palette_index.get(&bg_rgb.as_color_rgb()),
+                link: None,
+                protected: false,
             }
+        };
+        cell.set_chars(codes, unsafe { ffi::vterm_cell_get_width(ptr) as u8 });
+        cell
+    }
+
+    /// Overwrites this cell with one copied out by vterm_screen_get_snapshot, which does have
+    /// protection. The chars Vec is reused. Unlike from_ptr this leaves the palette indexes and
+    /// link alone, so the caller can look them up.
+    pub fn set_from_snapshot_cell(&mut self, cell: &ffi::VTermSnapshotCell) {
+        let chars_count = cell.chars.iter().position(|&c| c == 0).unwrap_or(cell.chars.len());
+        self.set_chars(&cell.chars[0..chars_count], cell.width as u8);
+        self.attrs = ScreenCellAttr {
+            bold: cell.bold != 0,
+            underline: cell.underline,
+            italic: cell.italic != 0,
+            blink: cell.blink != 0,
+            reverse: cell.reverse != 0,
+            strike: cell.strike != 0,
+            font: cell.font,
+            dwl: cell.dwl != 0,
+            dhl: cell.dhl,
+        };
+        self.fg_rgb = cell.fg.as_color_rgb();
+        self.bg_rgb = cell.bg.as_color_rgb();
+        self.protected = cell.protected_cell != 0;
+    }
+
+    // Sets chars and width from libvterm's code points, reusing the chars Vec. libvterm marks the
+    // cell after a wide character with an invalid code point, which has no chars and no width.
+    fn set_chars(&mut self, codes: &[uint32_t], width: u8) {
+        self.chars.clear();
+        if codes.first() == Some(&WIDE_CONTINUATION) {
+            self.width = 0;
+        } else {
+            self.chars.extend(codes.iter().filter_map(|&code| ::char_from_u32(code)));
+            self.width = width;
+        }
+    }
+
+    /// Copies this cell's data into the cell at the given pointer, the reverse of from_ptr. The
+    /// palette indexes and link aren't written since libvterm cells only hold rgb colors.
+    pub fn write_to_ptr(&self, ptr: *mut ffi::VTermScreenCell) {
+        let mut buf = [0 as uint32_t; ffi::VTERM_MAX_CHARS_PER_CELL];
+        if self.is_wide_continuation() {
+            buf[0] = WIDE_CONTINUATION;
+        }
+        // screen.c copies chars up to a 0, so one always has to be left at the end
+        for (i, &c) in self.chars.iter().take(ffi::VTERM_MAX_CHARS_PER_CELL - 1).enumerate() {
+            buf[i] = c as uint32_t;
+        }
+        // libvterm's own cells are never narrower than 1
+        let width = if self.is_wide_continuation() { 1 } else { self.width };
+
+        unsafe {
+            ffi::vterm_cell_set_chars(ptr, buf.as_ptr(), ffi::VTERM_MAX_CHARS_PER_CELL as size_t);
+            ffi::vterm_cell_set_width(ptr, width as c_char);
+            ffi::vterm_cell_set_bold(ptr, self.attrs.bold as c_uint);
+            ffi::vterm_cell_set_underline(ptr, self.attrs.underline as c_uint);
+            ffi::vterm_cell_set_italic(ptr, self.attrs.italic as c_uint);
+            ffi::vterm_cell_set_blink(ptr, self.attrs.blink as c_uint);
+            ffi::vterm_cell_set_reverse(ptr, self.attrs.reverse as c_uint);
+            ffi::vterm_cell_set_strike(ptr, self.attrs.strike as c_uint);
+            ffi::vterm_cell_set_font(ptr, self.attrs.font as c_uint);
+            ffi::vterm_cell_set_dwl(ptr, self.attrs.dwl as c_uint);
+            ffi::vterm_cell_set_dhl(ptr, self.attrs.dhl as c_uint);
+            ffi::vterm_cell_set_fg(ptr,
+                                   ffi::VTermColor {
+                                       red: self.fg_rgb.red,
+                                       green: self.fg_rgb.green,
+                                       blue: self.fg_rgb.blue,
+                                   });
+            ffi::vterm_cell_set_bg(ptr,
+                                   ffi::VTermColor {
+                                       red: self.bg_rgb.red,
+                                       green: self.bg_rgb.green,
+                                       blue: self.bg_rgb.blue,
+                                   });
         }
     }
+
+    /// The cell's character and any combining characters as a string. Empty for an erased cell
+    /// and for the cell after a wide character.
+    pub fn text(&self) -> String {
+        self.chars.iter().cloned().collect()
+    }
+
+    /// Whether this is the cell after a wide character, which the wide character covers.
+    pub fn is_wide_continuation(&self) -> bool {
+        self.width == 0
+    }
 }
 
 impl Default for ScreenCell {
@@ -105,8 +208,10 @@ impl Default for ScreenCell {
                 green: 5,
                 blue: 5,
             },
-            fg_palette: 7,
-            bg_palette: 0,
+            fg_palette: None,
+            bg_palette: None,
+            link: None,
+            protected: false,
         }
     }
 }
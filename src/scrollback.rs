@@ -0,0 +1,149 @@
+use std::collections::VecDeque;
+
+use super::*;
+
+/// Lines that have scrolled off the top of the screen, most recent first. libvterm pushes a line
+/// here every time one scrolls off, and pops lines back when the screen grows taller.
+#[derive(Debug, Default, Clone)]
+pub struct Scrollback {
+    lines: VecDeque<Line>,
+    limit: usize,
+}
+
+#[derive(Debug, Default, Clone)]
+struct Line {
+    cells: Vec<ScreenCell>,
+    marks: Vec<RowMark>,
+}
+
+impl Scrollback {
+    /// Keeps at most `limit` lines. The oldest lines are dropped first.
+    pub fn new(limit: usize) -> Scrollback {
+        Scrollback {
+            lines: VecDeque::new(),
+            limit: limit,
+        }
+    }
+
+    pub fn limit(&self) -> usize {
+        self.limit
+    }
+
+    /// Change the line limit, dropping the oldest lines if there are now too many.
+    pub fn set_limit(&mut self, limit: usize) {
+        self.limit = limit;
+        self.lines.truncate(limit);
+    }
+
+    pub fn len(&self) -> usize {
+        self.lines.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lines.is_empty()
+    }
+
+    /// Returns line `n` of history, where 0 is the line that most recently scrolled off the
+    /// screen, or None if there aren't that many lines.
+    pub fn line(&self, n: usize) -> Option<&[ScreenCell]> {
+        self.lines.get(n).map(|line| &line.cells[..])
+    }
+
+    /// Returns the shell integration marks that were set on line `n` while it was on the screen.
+    pub fn marks(&self, n: usize) -> Option<&[RowMark]> {
+        self.lines.get(n).map(|line| &line.marks[..])
+    }
+
+    /// Add a line that just scrolled off the screen.
+    pub fn push(&mut self, cells: Vec<ScreenCell>) {
+        self.push_with_marks(cells, vec![]);
+    }
+
+    /// Add a line that just scrolled off the screen, along with its marks.
+    pub fn push_with_marks(&mut self, cells: Vec<ScreenCell>, marks: Vec<RowMark>) {
+        if self.limit == 0 {
+            return;
+        }
+
+        self.lines.truncate(self.limit - 1);
+        self.lines.push_front(Line {
+            cells: cells,
+            marks: marks,
+        });
+    }
+
+    /// Remove and return the line that most recently scrolled off the screen.
+    pub fn pop(&mut self) -> Option<Vec<ScreenCell>> {
+        self.pop_with_marks().map(|(cells, _)| cells)
+    }
+
+    /// Remove and return the line that most recently scrolled off the screen, and its marks.
+    pub fn pop_with_marks(&mut self) -> Option<(Vec<ScreenCell>, Vec<RowMark>)> {
+        self.lines.pop_front().map(|line| (line.cells, line.marks))
+    }
+
+    pub fn clear(&mut self) {
+        self.lines.clear();
+    }
+}
+
+impl VTerm {
+    /// The lines that have scrolled off the top of the screen.
+    pub fn scrollback(&self) -> &Scrollback {
+        &self.inner.scrollback
+    }
+
+    /// Set how many lines of scrollback to keep. Zero turns scrollback off.
+    pub fn set_scrollback_limit(&mut self, limit: usize) {
+        self.inner.scrollback.set_limit(limit);
+    }
+}
+
+mod tests {
+    #![allow(unused_imports)]
+    #![allow(dead_code)]
+
+    use super::super::*;
+
+    fn line(c: u8) -> Vec<ScreenCell> {
+        let mut cell: ScreenCell = Default::default();
+        cell.chars = vec![c as char];
+        vec![cell]
+    }
+
+    #[test]
+    fn scrollback_returns_most_recent_line_first() {
+        let mut scrollback = Scrollback::new(10);
+        scrollback.push(line(b'a'));
+        scrollback.push(line(b'b'));
+
+        assert_eq!(scrollback.len(), 2);
+        assert_eq!(scrollback.line(0), Some(&line(b'b')[..]));
+        assert_eq!(scrollback.line(1), Some(&line(b'a')[..]));
+        assert_eq!(scrollback.line(2), None);
+
+        assert_eq!(scrollback.pop(), Some(line(b'b')));
+        assert_eq!(scrollback.pop(), Some(line(b'a')));
+        assert_eq!(scrollback.pop(), None);
+    }
+
+    #[test]
+    fn scrollback_drops_oldest_lines_over_the_limit() {
+        let mut scrollback = Scrollback::new(2);
+        scrollback.push(line(b'a'));
+        scrollback.push(line(b'b'));
+        scrollback.push(line(b'c'));
+
+        assert_eq!(scrollback.len(), 2);
+        assert_eq!(scrollback.line(0), Some(&line(b'c')[..]));
+        assert_eq!(scrollback.line(1), Some(&line(b'b')[..]));
+
+        scrollback.set_limit(1);
+        assert_eq!(scrollback.len(), 1);
+        assert_eq!(scrollback.line(0), Some(&line(b'c')[..]));
+
+        scrollback.set_limit(0);
+        scrollback.push(line(b'd'));
+        assert!(scrollback.is_empty());
+    }
+}
@@ -0,0 +1,151 @@
+use std::cmp::min;
+
+use super::*;
+
+/// The shell integration marks from OSC 133, first used by FinalTerm. A shell that supports them
+/// marks where each prompt, command line and command output starts.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum ShellMark {
+    /// OSC 133 ; A
+    PromptStart,
+    /// OSC 133 ; B
+    CommandStart,
+    /// OSC 133 ; C
+    OutputStart,
+    /// OSC 133 ; D with the exit status, if the shell gave one
+    CommandFinished { exit_status: Option<i32> },
+}
+
+impl ShellMark {
+    /// Parse the arguments after `133;`, like `D;1`.
+    pub fn parse(args: &[u8]) -> Option<ShellMark> {
+        let mut parts = args.split(|&b| b == b';');
+        match parts.next() {
+            Some(b"A") => Some(ShellMark::PromptStart),
+            Some(b"B") => Some(ShellMark::CommandStart),
+            Some(b"C") => Some(ShellMark::OutputStart),
+            Some(b"D") => {
+                let exit_status = parts.next()
+                    .and_then(|status| ::std::str::from_utf8(status).ok())
+                    .and_then(|status| status.parse().ok());
+                Some(ShellMark::CommandFinished { exit_status: exit_status })
+            }
+            _ => None,
+        }
+    }
+}
+
+/// A mark recorded against a row, with the column the cursor was in when it was set.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct RowMark {
+    pub mark: ShellMark,
+    pub col: usize,
+}
+
+impl VTerm {
+    /// The marks recorded against a screen row, in the order they were set.
+    pub fn screen_row_marks(&self, row: usize) -> &[RowMark] {
+        self.inner.tracker.row_marks(row)
+    }
+
+    /// The output of the last command that finished, from its OutputStart mark to its
+    /// CommandFinished mark, which may have scrolled into the scrollback. Trailing spaces are
+    /// trimmed from each line. Returns None if no command has finished since the shell started
+    /// sending marks, or the start of its output is no longer in the scrollback.
+    pub fn last_command_output(&self) -> Option<String> {
+        let scrollback_len = self.inner.scrollback.len();
+        let lines = scrollback_len + self.get_size().height;
+
+        // Walk backwards to the last finished command, then on to the start of its output
+        let mut end = None;
+        let mut start = None;
+        'lines: for line in (0..lines).rev() {
+            for row_mark in self.line_marks(line).iter().rev() {
+                match (row_mark.mark, end.is_some()) {
+                    (ShellMark::CommandFinished { .. }, false) => end = Some((line, row_mark.col)),
+                    (ShellMark::OutputStart, true) => {
+                        start = Some((line, row_mark.col));
+                        break 'lines;
+                    }
+                    (ShellMark::CommandFinished { .. }, true) |
+                    (ShellMark::PromptStart, true) => return None,
+                    _ => {}
+                }
+            }
+        }
+
+        let (start, end) = match (start, end) {
+            (Some(start), Some(end)) => (start, end),
+            _ => return None,
+        };
+
+        let mut text: Vec<String> = vec![];
+        for line in start.0..(end.0 + 1) {
+            let cells = self.line_cells(line);
+            let from = if line == start.0 { start.1 } else { 0 };
+            let to = if line == end.0 { end.1 } else { cells.len() };
+
+            // The finished mark is normally at the start of the line after the output
+            if line == end.0 && line != start.0 && to == 0 {
+                break;
+            }
+
+            let to = min(to, cells.len());
+            text.push(cells_text(&cells[min(from, to)..to]));
+        }
+
+        Some(text.join("\n"))
+    }
+
+    /// Lines count from the oldest scrollback line through to the bottom row of the screen.
+    fn line_marks(&self, line: usize) -> &[RowMark] {
+        let scrollback_len = self.inner.scrollback.len();
+        if line < scrollback_len {
+            self.inner.scrollback.marks(scrollback_len - 1 - line).unwrap_or(&[])
+        } else {
+            self.inner.tracker.row_marks(line - scrollback_len)
+        }
+    }
+
+    fn line_cells(&self, line: usize) -> Vec<ScreenCell> {
+        let scrollback_len = self.inner.scrollback.len();
+        if line < scrollback_len {
+            self.inner.scrollback.line(scrollback_len - 1 - line).unwrap_or(&[]).to_vec()
+        } else {
+            let width = self.get_size().width;
+            let row = Rect::new(Pos::new(0, line - scrollback_len), Size::new(width, 1));
+            self.screen_get_cells_in_rect(&row)
+        }
+    }
+}
+
+/// The text of a run of cells, with blank cells as spaces and trailing spaces trimmed.
+fn cells_text(cells: &[ScreenCell]) -> String {
+    let mut text = String::new();
+    for cell in cells {
+        if cell.chars.is_empty() && !cell.is_wide_continuation() {
+            text.push(' ');
+        }
+        text.push_str(&cell.text());
+    }
+    text.trim_right_matches(' ').to_string()
+}
+
+mod tests {
+    #![allow(unused_imports)]
+    use super::super::*;
+
+    #[test]
+    fn shell_mark_parses_osc_133_args() {
+        assert_eq!(ShellMark::parse(b"A"), Some(ShellMark::PromptStart));
+        assert_eq!(ShellMark::parse(b"B"), Some(ShellMark::CommandStart));
+        assert_eq!(ShellMark::parse(b"C"), Some(ShellMark::OutputStart));
+        assert_eq!(ShellMark::parse(b"D"),
+                   Some(ShellMark::CommandFinished { exit_status: None }));
+        assert_eq!(ShellMark::parse(b"D;2"),
+                   Some(ShellMark::CommandFinished { exit_status: Some(2) }));
+        assert_eq!(ShellMark::parse(b"A;aid=1"), Some(ShellMark::PromptStart));
+        assert_eq!(ShellMark::parse(b"Z"), None);
+        assert_eq!(ShellMark::parse(b""), None);
+    }
+}
@@ -0,0 +1,112 @@
+use libc::{c_int, size_t};
+
+use super::*;
+
+/// Everything on the screen at one moment, copied out of libvterm in one go. Keep one around and
+/// refill it with `VTerm::screen_snapshot_into` to reuse its buffers.
+#[derive(Debug, Clone)]
+pub struct ScreenSnapshot {
+    pub size: Size,
+    pub cursor: Pos,
+    /// Row by row, so the cell at pos is at `pos.y * size.width + pos.x`
+    pub cells: Vec<ScreenCell>,
+    buffer: Vec<ffi::VTermSnapshotCell>,
+}
+
+impl ScreenSnapshot {
+    pub fn new() -> ScreenSnapshot {
+        ScreenSnapshot {
+            size: Size::new(0, 0),
+            cursor: Pos::new(0, 0),
+            cells: vec![],
+            buffer: vec![],
+        }
+    }
+
+    pub fn cell(&self, pos: &Pos) -> Option<&ScreenCell> {
+        if pos.x >= self.size.width {
+            return None;
+        }
+        self.cells.get(pos.y * self.size.width + pos.x)
+    }
+
+    pub fn row(&self, row: usize) -> Option<&[ScreenCell]> {
+        if row >= self.size.height {
+            return None;
+        }
+        let start = row * self.size.width;
+        Some(&self.cells[start..start + self.size.width])
+    }
+}
+
+impl Default for ScreenSnapshot {
+    fn default() -> ScreenSnapshot {
+        ScreenSnapshot::new()
+    }
+}
+
+impl VTerm {
+    pub fn screen_snapshot(&self) -> ScreenSnapshot {
+        let mut snapshot = ScreenSnapshot::new();
+        self.screen_snapshot_into(&mut snapshot);
+        snapshot
+    }
+
+    /// Fill the snapshot with the current screen, reusing its cells. This is much cheaper than
+    /// screen_get_cells_in_rect for the whole screen, since libvterm copies all the cells into
+    /// the snapshot's buffer with one call.
+    pub fn screen_snapshot_into(&self, snapshot: &mut ScreenSnapshot) {
+        let size = self.get_size();
+        let len = size.width * size.height;
+        snapshot.buffer.resize(len, Default::default());
+
+        let mut cursor = ffi::VTermPos { row: 0, col: 0 };
+        let copied = unsafe {
+            ffi::vterm_screen_get_snapshot(self.screen_ptr.get(),
+                                           self.state_ptr.get(),
+                                           size.height as c_int,
+                                           size.width as c_int,
+                                           snapshot.buffer.as_mut_ptr(),
+                                           len as size_t,
+                                           &mut cursor)
+        };
+        // The buffer was sized for the screen, so this can't happen
+        assert_eq!(copied as usize, len);
+
+        let palette_index = &self.inner.palette_index;
+        let tracker = &self.inner.tracker;
+        // Existing cells are overwritten in place so their chars Vecs are reused
+        snapshot.cells.resize(len, Default::default());
+        // Neighbouring cells usually share colors, so remember the last palette lookups
+        let mut last_fg = None;
+        let mut last_bg = None;
+        for (i, (cell, raw)) in snapshot.cells.iter_mut().zip(&snapshot.buffer).enumerate() {
+            cell.set_from_snapshot_cell(raw);
+            cell.fg_palette = lookup_palette(palette_index, &mut last_fg, &cell.fg_rgb);
+            cell.bg_palette = lookup_palette(palette_index, &mut last_bg, &cell.bg_rgb);
+            // Erased cells keep their old link in the tracker, but a blank cell has no link
+            cell.link = if !cell.chars.is_empty() || cell.is_wide_continuation() {
+                tracker.link(&Pos::new(i % size.width, i / size.width))
+            } else {
+                None
+            };
+        }
+
+        snapshot.size = size;
+        snapshot.cursor = cursor.as_pos();
+    }
+}
+
+fn lookup_palette(palette_index: &PaletteIndex,
+                  last: &mut Option<(ColorRGB, Option<ColorPalette>)>,
+                  rgb: &ColorRGB)
+                  -> Option<ColorPalette> {
+    if let Some((ref last_rgb, palette)) = *last {
+        if last_rgb == rgb {
+            return palette;
+        }
+    }
+    let palette = palette_index.get(rgb);
+    *last = Some((rgb.clone(), palette));
+    palette
+}
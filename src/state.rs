@@ -1,4 +1,5 @@
-use libc::{c_int, c_void};
+use libc::{c_char, c_int, c_void};
+use std::ffi::CString;
 use std::sync::mpsc;
 
 use super::*;
@@ -133,25 +134,36 @@ impl VTerm {
         }
     }
 
-    pub fn state_get_palette_color_from_rgb(&self, target: &ColorRGB) -> usize {
-        for i in 0..256 {
-            let color = self.state_get_rgb_color_from_palette(i);
-            if color.red == target.red && color.green == target.green && color.blue == target.blue {
-                return i as usize;
-            }
-        }
-        0
+    pub fn state_get_palette_color_from_rgb(&self, target: &ColorRGB) -> Option<ColorPalette> {
+        self.inner.palette_index.get(target)
     }
 
     /// move this to ffi classes since it deals with the ffi color type
-    pub fn state_get_palette_color_from_c_rgb(&self, target: &ffi::VTermColor) -> usize {
-        for i in 0..256 {
-            let color = self.state_get_rgb_color_from_palette(i);
-            if color.red == target.red && color.green == target.green && color.blue == target.blue {
-                return i as usize;
-            }
-        }
-        0
+    pub fn state_get_palette_color_from_c_rgb(&self,
+                                              target: &ffi::VTermColor)
+                                              -> Option<ColorPalette> {
+        self.inner.palette_index.get(&target.as_color_rgb())
+    }
+
+    /// Where the cursor is now, for when events about it weren't being listened for.
+    pub fn cursor_pos(&self) -> Pos {
+        let mut pos = ffi::VTermPos { row: 0, col: 0 };
+        unsafe { ffi::vterm_state_get_cursorpos(self.state_ptr.get(), &mut pos) };
+        pos.as_pos()
+    }
+
+    /// Whether bold text in colors 0 to 7 is drawn in the bright colors 8 to 15 instead. Off by
+    /// default.
+    pub fn bold_is_highbright(&self) -> bool {
+        int_to_bool(unsafe { ffi::vterm_state_get_bold_highbright(self.state_ptr.get()) })
+    }
+
+    /// Only affects text written afterwards, since the pen color is worked out when SGR is seen.
+    pub fn set_bold_is_highbright(&mut self, is_highbright: bool) {
+        unsafe {
+            ffi::vterm_state_set_bold_highbright(self.state_ptr.get_mut(),
+                                                 ::bool_to_int(is_highbright))
+        };
     }
 
     pub fn state_reset(&mut self, hard: bool) {
@@ -160,7 +172,118 @@ impl VTerm {
         }
     }
 
+    /// Set a terminal property as if an escape sequence had, which also sends the usual callbacks
+    /// and events. Returns false if a callback refused the change, or a string has a nul byte in
+    /// it.
+    pub fn state_set_term_prop(&mut self, prop: &TermProp) -> bool {
+        let state_ptr = unsafe { self.state_ptr.get_mut() };
+        let ret = unsafe {
+            match *prop {
+                TermProp::CursorVisible(val) => {
+                    ffi::vterm_state_set_termprop_boolean(state_ptr,
+                                                          ffi::VTermProp::VTermPropCursorVisible,
+                                                          ::bool_to_int(val))
+                }
+                TermProp::CursorBlink(val) => {
+                    ffi::vterm_state_set_termprop_boolean(state_ptr,
+                                                          ffi::VTermProp::VTermPropCursorBlink,
+                                                          ::bool_to_int(val))
+                }
+                TermProp::AltScreen(val) => {
+                    ffi::vterm_state_set_termprop_boolean(state_ptr,
+                                                          ffi::VTermProp::VTermPropAltscreen,
+                                                          ::bool_to_int(val))
+                }
+                TermProp::Title(ref val) => {
+                    let val = match CString::new(val.as_bytes()) {
+                        Ok(val) => val,
+                        Err(_) => return false,
+                    };
+                    ffi::vterm_state_set_termprop_string(state_ptr,
+                                                         ffi::VTermProp::VTermPropTitle,
+                                                         val.as_ptr() as *mut c_char)
+                }
+                TermProp::IconName(ref val) => {
+                    let val = match CString::new(val.as_bytes()) {
+                        Ok(val) => val,
+                        Err(_) => return false,
+                    };
+                    ffi::vterm_state_set_termprop_string(state_ptr,
+                                                         ffi::VTermProp::VTermPropIconName,
+                                                         val.as_ptr() as *mut c_char)
+                }
+                TermProp::Reverse(val) => {
+                    ffi::vterm_state_set_termprop_boolean(state_ptr,
+                                                          ffi::VTermProp::VTermPropReverse,
+                                                          ::bool_to_int(val))
+                }
+                TermProp::CursorShape(ref val) => {
+                    ffi::vterm_state_set_termprop_number(state_ptr,
+                                                         ffi::VTermProp::VTermPropCursorShape,
+                                                         val.clone() as c_int)
+                }
+                TermProp::Mouse(ref val) => {
+                    ffi::vterm_state_set_termprop_number(state_ptr,
+                                                         ffi::VTermProp::VTermPropMouse,
+                                                         val.clone() as c_int)
+                }
+            }
+        };
+        int_to_bool(ret)
+    }
+
+    pub fn set_cursor_visible(&mut self, is_visible: bool) -> bool {
+        self.state_set_term_prop(&TermProp::CursorVisible(is_visible))
+    }
+
+    pub fn set_cursor_blink(&mut self, is_blinking: bool) -> bool {
+        self.state_set_term_prop(&TermProp::CursorBlink(is_blinking))
+    }
+
+    pub fn set_cursor_shape(&mut self, shape: CursorShape) -> bool {
+        self.state_set_term_prop(&TermProp::CursorShape(shape))
+    }
+
+    pub fn set_title(&mut self, title: &str) -> bool {
+        self.state_set_term_prop(&TermProp::Title(title.to_string()))
+    }
+
+    /// Switch to or from the alternate screen. The screen needs `screen_enable_altscreen` first.
+    pub fn set_altscreen(&mut self, is_on: bool) -> bool {
+        self.state_set_term_prop(&TermProp::AltScreen(is_on))
+    }
+
+    pub fn set_mouse_mode(&mut self, mode: MouseMode) -> bool {
+        self.state_set_term_prop(&TermProp::Mouse(mode))
+    }
+
+    /// Setup the vterm to generate StateEvent messages to a channel, whose receiver end is in the
+    /// state_event_rx field. This replaces any handler given to set_state_handler.
+    ///
+    /// Note that libvterm's screen layer is itself built on the state callbacks, so registering
+    /// these replaces the screen's and screen events will no longer be generated. Hyperlinks stop
+    /// being tracked too.
     pub fn state_receive_events(&mut self, config: &StateCallbacksConfig) {
+        if self.state_event_tx.is_none() {
+            let (tx, rx) = mpsc::channel();
+            self.state_event_tx = Some(tx);
+            self.state_event_rx = Some(rx);
+        }
+
+        let handler = ChannelStateHandler::new(self.state_event_tx.as_ref().unwrap().clone());
+        self.register_state_handler(Box::new(handler), config);
+    }
+
+    /// Call the handler's methods synchronously for every state callback, while input is being
+    /// written. This replaces the channel set up by state_receive_events. As with that, the
+    /// screen layer's callbacks are replaced.
+    pub fn set_state_handler(&mut self, handler: Box<StateHandler>) {
+        self.register_state_handler(handler, &StateCallbacksConfig::all());
+    }
+
+    fn register_state_handler(&mut self,
+                              handler: Box<StateHandler>,
+                              config: &StateCallbacksConfig) {
         let mut callbacks: ffi::VTermStateCallbacks = Default::default();
 
         // Note: Some of these seem to be required or libvterm will segfault
@@ -220,19 +343,14 @@ impl VTerm {
             None
         };
 
-        self.state_callbacks = Some(callbacks);
-
-        if self.state_event_tx.is_none() {
-            let (tx, rx) = mpsc::channel();
-            self.state_event_tx = Some(tx);
-            self.state_event_rx = Some(rx);
-        }
+        self.inner.state_callbacks = Some(callbacks);
+        self.inner.state_handler = Some(handler);
 
         unsafe {
-            let self_ptr: *mut c_void = self as *mut _ as *mut c_void;
+            let inner_ptr: *mut c_void = &mut *self.inner as *mut _ as *mut c_void;
             ffi::vterm_state_set_callbacks(self.state_ptr.get_mut(),
-                                           self.state_callbacks.as_ref().unwrap(),
-                                           self_ptr);
+                                           self.inner.state_callbacks.as_ref().unwrap(),
+                                           inner_ptr);
         }
     }
 }
@@ -1,279 +1,238 @@
 #![allow(unused_variables)]
 
 use libc::{c_int, c_void};
-use std::sync::mpsc::Sender;
 use std::ffi::CStr;
 
 use super::*;
+use inner::with_inner;
 
 pub extern "C" fn put_glyph(info: *mut ffi::VTermGlyphInfo,
                             pos: ffi::VTermPos,
-                            vterm: *mut c_void)
+                            inner: *mut c_void)
                             -> c_int {
-    cast_vterm(vterm, |vterm, tx| {
-        let event = StateEvent::PutGlyph(PutGlyphEvent {
-            glyph_info: ::GlyphInfo::from_ptr(info),
-            pos: pos.as_pos(),
-        });
-
-        match tx.send(event) {
-            Ok(_) => 1,
-            Err(_) => 0,
-        }
+    with_handler(inner, |palette_index, handler| {
+        handler.put_glyph(&::GlyphInfo::from_ptr(info), pos.as_pos())
     })
 }
 
 pub extern "C" fn move_cursor(new: ffi::VTermPos,
                               old: ffi::VTermPos,
                               visible: c_int,
-                              vterm: *mut c_void)
+                              inner: *mut c_void)
                               -> c_int {
-    cast_vterm(vterm, |vterm, tx| {
-        let event = StateEvent::MoveCursor(MoveCursorEvent {
-            new: new.as_pos(),
-            old: old.as_pos(),
-            is_visible: int_to_bool(visible),
-        });
-
-        match tx.send(event) {
-            Ok(_) => 1,
-            Err(_) => 0,
-        }
+    with_handler(inner, |palette_index, handler| {
+        handler.move_cursor(new.as_pos(), old.as_pos(), int_to_bool(visible))
     })
 }
 
 pub extern "C" fn scroll_rect(rect: ffi::VTermRect,
                               downward: c_int,
                               rightward: c_int,
-                              vterm: *mut c_void)
+                              inner: *mut c_void)
                               -> c_int {
-    cast_vterm(vterm, |vterm, tx| {
-        let event = StateEvent::ScrollRect(ScrollRectEvent {
-            rect: rect.as_rect(),
-            downward: downward as isize,
-            rightward: rightward as isize,
-        });
-
-        match tx.send(event) {
-            Ok(_) => 1,
-            Err(_) => 0,
-        }
+    with_handler(inner, |palette_index, handler| {
+        handler.scroll_rect(rect.as_rect(), downward as isize, rightward as isize)
     })
 }
 
 pub extern "C" fn move_rect(dest: ffi::VTermRect,
                             src: ffi::VTermRect,
-                            vterm: *mut c_void)
+                            inner: *mut c_void)
                             -> c_int {
-    cast_vterm(vterm, |vterm, tx| {
-        let event = StateEvent::MoveRect(MoveRectEvent {
-            src: src.as_rect(),
-            dest: dest.as_rect(),
-        });
-
-        match tx.send(event) {
-            Ok(_) => 1,
-            Err(_) => 0,
-        }
-    })
+    with_handler(inner,
+                 |palette_index, handler| handler.move_rect(dest.as_rect(), src.as_rect()))
 }
 
-pub extern "C" fn erase(rect: ffi::VTermRect, selective: c_int, vterm: *mut c_void) -> c_int {
-    cast_vterm(vterm, |vterm, tx| {
-        let event = StateEvent::Erase(EraseEvent {
-            rect: rect.as_rect(),
-            is_selective: int_to_bool(selective),
-        });
-
-        match tx.send(event) {
-            Ok(_) => 1,
-            Err(_) => 0,
-        }
+pub extern "C" fn erase(rect: ffi::VTermRect, selective: c_int, inner: *mut c_void) -> c_int {
+    with_handler(inner, |palette_index, handler| {
+        handler.erase(rect.as_rect(), int_to_bool(selective))
     })
 }
 
-pub extern "C" fn init_pen(vterm: *mut c_void) -> c_int {
-    cast_vterm(vterm, |vterm, tx| {
-        let event = StateEvent::InitPen(InitPenEvent);
-        match tx.send(event) {
-            Ok(_) => 1,
-            Err(_) => 0,
-        }
-    })
+pub extern "C" fn init_pen(inner: *mut c_void) -> c_int {
+    with_handler(inner, |palette_index, handler| handler.init_pen())
 }
 
 pub extern "C" fn set_pen_attr(attr: ffi::VTermAttr,
                                val: *mut ffi::VTermValue,
-                               vterm: *mut c_void)
+                               inner: *mut c_void)
                                -> c_int {
-    cast_vterm(vterm, |vterm, tx| {
-        let event: StateEvent = match attr {
+    with_handler(inner, |palette_index, handler| {
+        let attr: PenAttr = match attr {
             ffi::VTermAttr::Bold => {
                 let val = unsafe { int_to_bool(ffi::vterm_value_get_boolean(val)).clone() };
-                StateEvent::PenBold(PenBoldEvent { is_on: val })
+                PenAttr::Bold(val)
             }
             ffi::VTermAttr::Background => {
                 let rgb: ColorRGB = unsafe { ffi::vterm_value_get_color(val).as_color_rgb() };
-                let palette = vterm.state_get_palette_color_from_rgb(&rgb);
-                StateEvent::PenBackground(PenBackgroundEvent {
+                let palette = palette_index.get(&rgb);
+                PenAttr::Background {
                     rgb: rgb,
                     palette: palette,
-                })
+                }
             }
             ffi::VTermAttr::Blink => {
                 let val = unsafe { int_to_bool(ffi::vterm_value_get_boolean(val)).clone() };
-                StateEvent::PenBlink(PenBlinkEvent { is_on: val })
+                PenAttr::Blink(val)
             }
             ffi::VTermAttr::Font => {
                 let val = unsafe { ffi::vterm_value_get_number(val).clone() };
-                StateEvent::PenFont(PenFontEvent { font: val })
+                PenAttr::Font(val)
             }
             ffi::VTermAttr::Foreground => {
                 let rgb: ColorRGB = unsafe { ffi::vterm_value_get_color(val).as_color_rgb() };
-                let palette = vterm.state_get_palette_color_from_rgb(&rgb);
-                StateEvent::PenForeground(PenForegroundEvent {
+                let palette = palette_index.get(&rgb);
+                PenAttr::Foreground {
                     rgb: rgb,
                     palette: palette,
-                })
+                }
             }
             ffi::VTermAttr::Italic => {
                 let val = unsafe { int_to_bool(ffi::vterm_value_get_boolean(val)).clone() };
-                StateEvent::PenItalic(PenItalicEvent { is_on: val })
+                PenAttr::Italic(val)
             }
             ffi::VTermAttr::Reverse => {
                 let val = unsafe { int_to_bool(ffi::vterm_value_get_boolean(val)).clone() };
-                StateEvent::PenReverse(PenReverseEvent { is_on: val })
+                PenAttr::Reverse(val)
             }
             ffi::VTermAttr::Strike => {
                 let val = unsafe { int_to_bool(ffi::vterm_value_get_boolean(val)).clone() };
-                StateEvent::PenStrike(PenStrikeEvent { is_on: val })
+                PenAttr::Strike(val)
             }
             ffi::VTermAttr::Underline => {
-                let val = unsafe { Underline::from_i32(ffi::vterm_value_get_number(val).clone()) };
-                StateEvent::PenUnderline(PenUnderlineEvent { mode: val })
+                let val = unsafe { ffi::vterm_value_get_number(val) };
+                match Underline::try_from_i32(val) {
+                    Ok(mode) => PenAttr::Underline(mode),
+                    Err(e) => {
+                        warn!("{}", e);
+                        return false;
+                    }
+                }
             }
         };
 
-        match tx.send(event) {
-            Ok(_) => 1,
-            Err(_) => 0,
-        }
+        handler.set_pen_attr(attr)
     })
 }
 
 pub extern "C" fn set_term_prop(prop: ffi::VTermProp,
                                 val: *mut ffi::VTermValue,
-                                vterm: *mut c_void)
+                                inner: *mut c_void)
                                 -> c_int {
-    cast_vterm(vterm, |vterm, tx| {
-        let event: StateEvent = match prop {
-            ffi::VTermProp::VTermPropCursorVisible => {
-                let val = unsafe { int_to_bool(ffi::vterm_value_get_boolean(val)).clone() };
-                StateEvent::CursorVisible(CursorVisibleEvent { is_on: val })
-            }
+    with_handler(inner, |palette_index, handler| {
+        match term_prop_from_ptr(prop, val) {
+            Some(prop) => handler.set_term_prop(prop),
+            None => true,
+        }
+    })
+}
 
-            ffi::VTermProp::VTermPropAltscreen => {
-                let val = unsafe { int_to_bool(ffi::vterm_value_get_boolean(val)).clone() };
-                StateEvent::AltScreen(AltScreenEvent { is_on: val })
-            }
+/// Decode a property libvterm passed to a settermprop callback. Unknown values are logged and
+/// None is returned. The screen callbacks use this too.
+pub fn term_prop_from_ptr(prop: ffi::VTermProp, val: *const ffi::VTermValue) -> Option<TermProp> {
+    let prop: TermProp = match prop {
+        ffi::VTermProp::VTermPropCursorVisible => {
+            let val = unsafe { int_to_bool(ffi::vterm_value_get_boolean(val)).clone() };
+            TermProp::CursorVisible(val)
+        }
 
-            ffi::VTermProp::VTermPropCursorBlink => {
-                let val = unsafe { int_to_bool(ffi::vterm_value_get_boolean(val)).clone() };
-                StateEvent::CursorBlink(CursorBlinkEvent { is_on: val })
-            }
+        ffi::VTermProp::VTermPropAltscreen => {
+            let val = unsafe { int_to_bool(ffi::vterm_value_get_boolean(val)).clone() };
+            TermProp::AltScreen(val)
+        }
 
-            ffi::VTermProp::VTermPropCursorShape => {
-                let val: CursorShape = unsafe {
-                    CursorShape::from_i32(ffi::vterm_value_get_number(val))
-                };
-                StateEvent::CursorShape(CursorShapeEvent { shape: val })
-            }
+        ffi::VTermProp::VTermPropCursorBlink => {
+            let val = unsafe { int_to_bool(ffi::vterm_value_get_boolean(val)).clone() };
+            TermProp::CursorBlink(val)
+        }
 
-            ffi::VTermProp::VTermPropIconName => {
-                let val: String = unsafe {
-                    CStr::from_ptr(ffi::vterm_value_get_string(val)).to_string_lossy().into_owned()
-                };
-                StateEvent::IconName(IconNameEvent { name: val })
+        ffi::VTermProp::VTermPropCursorShape => {
+            let val = unsafe { ffi::vterm_value_get_number(val) };
+            match CursorShape::try_from_i32(val) {
+                Ok(shape) => TermProp::CursorShape(shape),
+                Err(e) => {
+                    warn!("{}", e);
+                    return None;
+                }
             }
+        }
 
-            ffi::VTermProp::VTermPropMouse => {
-                let val: MouseMode = unsafe {
-                    MouseMode::from_i32(ffi::vterm_value_get_number(val))
-                };
-                StateEvent::Mouse(MouseEvent { mode: val })
-            }
+        ffi::VTermProp::VTermPropIconName => {
+            let val: String = unsafe {
+                CStr::from_ptr(ffi::vterm_value_get_string(val)).to_string_lossy().into_owned()
+            };
+            TermProp::IconName(val)
+        }
 
-            ffi::VTermProp::VTermPropReverse => {
-                let val = unsafe { int_to_bool(ffi::vterm_value_get_boolean(val)).clone() };
-                StateEvent::Reverse(ReverseEvent { is_on: val })
+        ffi::VTermProp::VTermPropMouse => {
+            let val = unsafe { ffi::vterm_value_get_number(val) };
+            match MouseMode::try_from_i32(val) {
+                Ok(mode) => TermProp::Mouse(mode),
+                Err(e) => {
+                    warn!("{}", e);
+                    return None;
+                }
             }
+        }
 
-            ffi::VTermProp::VTermPropTitle => {
-                let val: String = unsafe {
-                    CStr::from_ptr(ffi::vterm_value_get_string(val)).to_string_lossy().into_owned()
-                };
-                StateEvent::Title(TitleEvent { title: val })
-            }
-        };
+        ffi::VTermProp::VTermPropReverse => {
+            let val = unsafe { int_to_bool(ffi::vterm_value_get_boolean(val)).clone() };
+            TermProp::Reverse(val)
+        }
 
-        match tx.send(event) {
-            Ok(_) => 1,
-            Err(_) => 0,
+        ffi::VTermProp::VTermPropTitle => {
+            let val: String = unsafe {
+                CStr::from_ptr(ffi::vterm_value_get_string(val)).to_string_lossy().into_owned()
+            };
+            TermProp::Title(val)
         }
-    })
+    };
+
+    Some(prop)
 }
 
-pub extern "C" fn bell(vterm: *mut c_void) -> c_int {
-    cast_vterm(vterm, |vterm, tx| {
-        let event = StateEvent::Bell(BellEvent);
-        match tx.send(event) {
-            Ok(_) => 1,
-            Err(_) => 0,
-        }
-    })
+pub extern "C" fn bell(inner: *mut c_void) -> c_int {
+    with_handler(inner, |palette_index, handler| handler.bell())
 }
 
 pub extern "C" fn resize(rows: c_int,
                          cols: c_int,
                          delta: *mut ffi::VTermPos,
-                         vterm: *mut c_void)
+                         inner: *mut c_void)
                          -> c_int {
     // NOTE: libvterm expects a synchronise api here and wants us to mutate delta based on how we
     // choose to scroll or something. But we can't do that can we?
 
-    cast_vterm(vterm, |vterm, tx| {
-        let event = StateEvent::Resize(ResizeEvent {
-            size: Size::new(cols as usize, rows as usize),
-        });
-        match tx.send(event) {
-            Ok(_) => 1,
-            Err(_) => 0,
-        }
+    with_handler(inner, |palette_index, handler| {
+        handler.resize(Size::new(cols as usize, rows as usize))
     })
 }
 pub extern "C" fn set_line_info(row: c_int,
                                 new: *const ffi::VTermLineInfo,
                                 old: *const ffi::VTermLineInfo,
-                                vterm: *mut c_void)
+                                inner: *mut c_void)
                                 -> c_int {
-    cast_vterm(vterm, |vterm, tx| {
-        let event = StateEvent::LineInfo(LineInfoEvent {
-            row: row, // TODO: add line info data as well
-        });
-        match tx.send(event) {
-            Ok(_) => 1,
-            Err(_) => 0,
+    with_handler(inner, |palette_index, handler| {
+        match (LineInfo::from_ptr(new), LineInfo::from_ptr(old)) {
+            (Ok(new), Ok(old)) => handler.set_line_info(row, new, old),
+            (Err(e), _) | (_, Err(e)) => {
+                warn!("{}", e);
+                true
+            }
         }
     })
 }
 
-/// Call the given closure with the vterms sender, if it exists.
-fn cast_vterm<F>(vterm: *mut c_void, closure: F) -> c_int
-    where F: Fn(&VTerm, &Sender<StateEvent>) -> c_int
+/// Call the given closure with the vterms state handler, if there is one. Panics are caught by
+/// with_inner.
+fn with_handler<F>(inner: *mut c_void, closure: F) -> c_int
+    where F: FnOnce(&PaletteIndex, &mut StateHandler) -> bool
 {
-    let vterm: &VTerm = unsafe { &mut *(vterm as *mut VTerm) };
-    match vterm.state_event_tx.as_ref() {
-        Some(tx) => closure(vterm, tx),
-        None => 0,
-    }
+    with_inner(inner, |inner| {
+        let palette_index = &inner.palette_index;
+        match inner.state_handler.as_mut() {
+            Some(handler) => if closure(palette_index, &mut **handler) { 1 } else { 0 },
+            None => 0,
+        }
+    })
 }
@@ -0,0 +1,410 @@
+// Keeps track of what libvterm's screen cells have no room for: which hyperlink each cell belongs
+// to and which shell integration marks were set on each row. It's moved around by the tracking
+// callbacks to match what the screen layer does with its own cells.
+
+use std::cmp;
+use std::collections::{HashMap, HashSet};
+
+use super::*;
+
+/// Interning at least this many links since the last sweep starts another one
+const SWEEP_MIN_LINKS: usize = 256;
+
+#[derive(Debug, Default, PartialEq, Clone)]
+pub struct Row {
+    pub links: Vec<Option<LinkId>>,
+    pub marks: Vec<RowMark>,
+}
+
+impl Row {
+    fn blank(width: usize) -> Row {
+        Row {
+            links: vec![None; width],
+            marks: vec![],
+        }
+    }
+}
+
+pub struct Tracker {
+    hyperlinks: HashMap<LinkId, Hyperlink>,
+    ids: HashMap<Hyperlink, LinkId>,
+    next_link_id: LinkId,
+    /// How many links were left after the last sweep
+    swept_len: usize,
+    /// The link new glyphs are written with. This is part of the pen, so a reset clears it.
+    pub active_link: Option<LinkId>,
+
+    size: Size,
+    primary: Vec<Row>,
+    alt: Vec<Row>,
+    /// Whether the screen layer has an alternate buffer to switch to
+    pub has_altscreen: bool,
+    is_altscreen: bool,
+
+    /// How many rows have gone to sb_pushline since the current scroll started
+    pushed: usize,
+    /// Rows given back by sb_popline, waiting for the screen to make room for them
+    popped: Vec<Row>,
+}
+
+impl Tracker {
+    pub fn new() -> Tracker {
+        Tracker {
+            hyperlinks: HashMap::new(),
+            ids: HashMap::new(),
+            next_link_id: 0,
+            swept_len: 0,
+            active_link: None,
+            size: Size::new(0, 0),
+            primary: vec![],
+            alt: vec![],
+            has_altscreen: false,
+            is_altscreen: false,
+            pushed: 0,
+            popped: vec![],
+        }
+    }
+
+    /// Returns the id for the hyperlink, reusing the old id if the same link was seen before.
+    pub fn intern(&mut self, hyperlink: Hyperlink) -> LinkId {
+        if let Some(&id) = self.ids.get(&hyperlink) {
+            return id;
+        }
+
+        let id = self.next_link_id;
+        self.next_link_id += 1;
+        self.hyperlinks.insert(id, hyperlink.clone());
+        self.ids.insert(hyperlink, id);
+        id
+    }
+
+    pub fn hyperlink(&self, id: LinkId) -> Option<&Hyperlink> {
+        self.hyperlinks.get(&id)
+    }
+
+    /// Whether enough links have been interned since the last sweep to look for unused ones again.
+    /// Waiting until the number of links doubles keeps the cost of sweeping proportional to the
+    /// number of links interned.
+    pub fn needs_sweep(&self) -> bool {
+        self.hyperlinks.len() >= cmp::max(SWEEP_MIN_LINKS, 2 * self.swept_len)
+    }
+
+    /// Forget the links that no cell on either screen or in the scrollback uses, and that aren't
+    /// the active link. Ids aren't reused, so a forgotten id just stops resolving.
+    pub fn sweep(&mut self, scrollback: &Scrollback) {
+        let mut in_use: HashSet<LinkId> = HashSet::new();
+        in_use.extend(self.active_link);
+        for row in self.primary.iter().chain(self.alt.iter()).chain(self.popped.iter()) {
+            in_use.extend(row.links.iter().filter_map(|&link| link));
+        }
+        for n in 0..scrollback.len() {
+            let cells = scrollback.line(n).unwrap_or(&[]);
+            in_use.extend(cells.iter().filter_map(|cell| cell.link));
+        }
+
+        let hyperlinks: HashMap<LinkId, Hyperlink> = self.hyperlinks
+            .drain()
+            .filter(|&(id, _)| in_use.contains(&id))
+            .collect();
+        self.ids = hyperlinks.iter().map(|(&id, link)| (link.clone(), id)).collect();
+        self.hyperlinks = hyperlinks;
+        self.swept_len = self.hyperlinks.len();
+    }
+
+    /// The link of the cell at pos. Erased cells are never cleared here, so callers should ignore
+    /// this for blank cells.
+    pub fn link(&self, pos: &Pos) -> Option<LinkId> {
+        self.buffer().get(pos.y).and_then(|row| row.links.get(pos.x)).and_then(|&link| link)
+    }
+
+    pub fn row_marks(&self, row: usize) -> &[RowMark] {
+        self.buffer().get(row).map(|row| &row.marks[..]).unwrap_or(&[])
+    }
+
+    /// A glyph was written at pos with the active link.
+    pub fn put_glyph(&mut self, pos: &Pos, width: usize) {
+        let active = self.active_link;
+        if let Some(row) = self.buffer_mut().get_mut(pos.y) {
+            for x in pos.x..(pos.x + width) {
+                if let Some(link) = row.links.get_mut(x) {
+                    *link = active;
+                }
+            }
+        }
+    }
+
+    pub fn add_mark(&mut self, pos: &Pos, mark: ShellMark) {
+        if let Some(row) = self.buffer_mut().get_mut(pos.y) {
+            row.marks.push(RowMark {
+                mark: mark,
+                col: pos.x,
+            });
+        }
+    }
+
+    /// Marks are dropped from whole rows an ED erases, apart from the cursor row. Shells erase
+    /// that with EL and ED 0 whenever they redraw the prompt, which mustn't lose the mark written
+    /// just before it, so it only loses its marks when the whole screen is erased. ED 0 from the
+    /// top left corner does that in two rects, the cursor row and then the rows below it.
+    pub fn erase(&mut self, rect: &Rect, cursor: &Pos) {
+        if rect.left() != 0 || rect.right() < self.size.width {
+            return;
+        }
+        let height = self.size.height;
+        let whole_screen = rect.top() == 0 && rect.bottom() >= height;
+        let rest_from_home = *cursor == Pos::new(0, 0) && rect.top() == 1 &&
+                             rect.bottom() >= height;
+        for (y, row) in self.buffer_mut().iter_mut().enumerate() {
+            let erased = y >= rect.top() && y < rect.bottom() && y != cursor.y;
+            if erased || whole_screen || (rest_from_home && y == 0) {
+                row.marks.clear();
+            }
+        }
+    }
+
+    /// Move the cells in rect the same way libvterm's vterm_scroll_rect does. Positive downward
+    /// moves content up.
+    pub fn scroll_rect(&mut self, rect: &Rect, downward: isize, rightward: isize) {
+        let width = self.size.width;
+        scroll_buffer(self.buffer_mut(), width, rect, downward, rightward);
+    }
+
+    /// Called before handing a scroll or resize to the screen layer, which may push rows to the
+    /// scrollback starting from the top.
+    pub fn start_scroll(&mut self) {
+        self.pushed = 0;
+        self.popped.clear();
+    }
+
+    /// The next row the screen layer is pushing to the scrollback.
+    pub fn next_pushed_row(&mut self) -> Row {
+        let row = self.primary.get(self.pushed).cloned().unwrap_or_default();
+        self.pushed += 1;
+        row
+    }
+
+    /// A row the screen layer took back from the scrollback.
+    pub fn popped_row(&mut self, row: Row) {
+        self.popped.push(row);
+    }
+
+    /// The screen was resized and moved its content down by `shift` rows, which is negative if
+    /// rows went to the scrollback and positive if they came back from it.
+    pub fn resize(&mut self, size: Size, shift: isize) {
+        if shift < 0 {
+            let rect = Rect::new(Pos::new(0, 0), self.size);
+            let width = self.size.width;
+            scroll_buffer(&mut self.primary, width, &rect, -shift, 0);
+        }
+
+        self.primary = resize_buffer(&self.primary, &size);
+        self.alt = resize_buffer(&self.alt, &size);
+        self.size = size;
+
+        let popped: Vec<Row> = self.popped.drain(..).collect();
+        for mut row in popped {
+            row.links.resize(self.size.width, None);
+            self.primary.pop();
+            self.primary.insert(0, row);
+        }
+    }
+
+    pub fn set_altscreen(&mut self, is_on: bool) {
+        if is_on && !self.has_altscreen {
+            return;
+        }
+        self.is_altscreen = is_on;
+    }
+
+    fn buffer(&self) -> &Vec<Row> {
+        if self.is_altscreen { &self.alt } else { &self.primary }
+    }
+
+    fn buffer_mut(&mut self) -> &mut Vec<Row> {
+        if self.is_altscreen {
+            &mut self.alt
+        } else {
+            &mut self.primary
+        }
+    }
+}
+
+/// Rows only take their marks with them when the whole width of the screen moves.
+fn scroll_buffer(buffer: &mut Vec<Row>,
+                 width: usize,
+                 rect: &Rect,
+                 downward: isize,
+                 rightward: isize) {
+    let old = buffer.clone();
+    let (top, bottom) = (rect.top() as isize, rect.bottom() as isize);
+    let (left, right) = (rect.left() as isize, rect.right() as isize);
+    let is_full_width = rect.left() == 0 && rect.right() >= width;
+
+    for y in top..bottom {
+        let src_y = y + downward;
+        let src_row = if src_y >= top && src_y < bottom {
+            old.get(src_y as usize)
+        } else {
+            None
+        };
+        let row = match buffer.get_mut(y as usize) {
+            Some(row) => row,
+            None => continue,
+        };
+
+        for x in left..right {
+            let src_x = x + rightward;
+            let link = match src_row {
+                Some(src_row) if src_x >= left && src_x < right => {
+                    src_row.links.get(src_x as usize).and_then(|&link| link)
+                }
+                _ => None,
+            };
+            if let Some(cell) = row.links.get_mut(x as usize) {
+                *cell = link;
+            }
+        }
+
+        if is_full_width {
+            row.marks = src_row.map(|src_row| src_row.marks.clone()).unwrap_or_default();
+        }
+    }
+}
+
+/// Keeps the top left of the old buffer, like libvterm's realloc_buffer
+fn resize_buffer(buffer: &[Row], size: &Size) -> Vec<Row> {
+    (0..size.height)
+        .map(|y| {
+            let mut row = buffer.get(y).cloned().unwrap_or_else(|| Row::blank(size.width));
+            row.links.resize(size.width, None);
+            row
+        })
+        .collect()
+}
+
+mod tests {
+    #![allow(unused_imports)]
+    #![allow(dead_code)]
+    use super::*;
+
+    fn link(uri: &str) -> Hyperlink {
+        Hyperlink {
+            uri: uri.to_string(),
+            params: vec![],
+        }
+    }
+
+    #[test]
+    fn tracker_interns_links() {
+        let mut tracker = Tracker::new();
+        let a = tracker.intern(link("a"));
+        let b = tracker.intern(link("b"));
+        assert!(a != b);
+        assert_eq!(tracker.intern(link("a")), a);
+        assert_eq!(tracker.hyperlink(b), Some(&link("b")));
+        assert_eq!(tracker.hyperlink(b + 1), None);
+    }
+
+    #[test]
+    fn tracker_sweep_forgets_unused_links() {
+        let mut tracker = Tracker::new();
+        tracker.resize(Size::new(2, 1), 0);
+        let on_screen = tracker.intern(link("on screen"));
+        tracker.active_link = Some(on_screen);
+        tracker.put_glyph(&Pos::new(0, 0), 1);
+        let unused = tracker.intern(link("unused"));
+        let in_scrollback = tracker.intern(link("in scrollback"));
+        tracker.active_link = Some(tracker.intern(link("active")));
+
+        let mut cell = ScreenCell::default();
+        cell.link = Some(in_scrollback);
+        let mut scrollback = Scrollback::new(10);
+        scrollback.push(vec![cell]);
+
+        assert!(!tracker.needs_sweep());
+        tracker.sweep(&scrollback);
+        assert_eq!(tracker.hyperlink(on_screen), Some(&link("on screen")));
+        assert_eq!(tracker.hyperlink(in_scrollback), Some(&link("in scrollback")));
+        assert_eq!(tracker.hyperlink(unused), None);
+        assert_eq!(tracker.intern(link("on screen")), on_screen);
+
+        // The unused link gets a new id, so anything holding the old one can't see the wrong link
+        let reinterned = tracker.intern(link("unused"));
+        assert!(reinterned != unused);
+        assert_eq!(tracker.hyperlink(unused), None);
+    }
+
+    #[test]
+    fn tracker_links_follow_scrolling() {
+        let mut tracker = Tracker::new();
+        tracker.resize(Size::new(3, 3), 0);
+        tracker.active_link = Some(tracker.intern(link("a")));
+        tracker.put_glyph(&Pos::new(1, 2), 2);
+
+        tracker.scroll_rect(&Rect::new(Pos::new(0, 0), Size::new(3, 3)), 1, 0);
+        assert_eq!(tracker.link(&Pos::new(1, 1)), Some(0));
+        assert_eq!(tracker.link(&Pos::new(2, 1)), Some(0));
+        assert_eq!(tracker.link(&Pos::new(0, 1)), None);
+        assert_eq!(tracker.link(&Pos::new(1, 2)), None);
+
+        tracker.scroll_rect(&Rect::new(Pos::new(0, 0), Size::new(3, 3)), 0, -1);
+        assert_eq!(tracker.link(&Pos::new(2, 1)), Some(0));
+        assert_eq!(tracker.link(&Pos::new(1, 1)), None);
+    }
+
+    #[test]
+    fn tracker_links_follow_resizing() {
+        let mut tracker = Tracker::new();
+        tracker.resize(Size::new(2, 2), 0);
+        tracker.active_link = Some(tracker.intern(link("a")));
+        tracker.put_glyph(&Pos::new(0, 1), 1);
+
+        tracker.start_scroll();
+        assert_eq!(tracker.next_pushed_row(), Row::blank(2));
+        tracker.resize(Size::new(2, 1), -1);
+        assert_eq!(tracker.link(&Pos::new(0, 0)), Some(0));
+
+        tracker.start_scroll();
+        tracker.popped_row(Row {
+            links: vec![Some(0)],
+            marks: vec![],
+        });
+        tracker.resize(Size::new(3, 2), 1);
+        assert_eq!(tracker.link(&Pos::new(0, 0)), Some(0));
+        assert_eq!(tracker.link(&Pos::new(0, 1)), Some(0));
+        assert_eq!(tracker.link(&Pos::new(2, 1)), None);
+    }
+
+    #[test]
+    fn tracker_marks_move_with_full_width_scrolls() {
+        let mut tracker = Tracker::new();
+        tracker.resize(Size::new(3, 3), 0);
+        tracker.add_mark(&Pos::new(1, 2), ShellMark::OutputStart);
+        let mark = RowMark {
+            mark: ShellMark::OutputStart,
+            col: 1,
+        };
+
+        tracker.scroll_rect(&Rect::new(Pos::new(1, 0), Size::new(2, 3)), 1, 0);
+        assert_eq!(tracker.row_marks(2), &[mark]);
+
+        tracker.scroll_rect(&Rect::new(Pos::new(0, 0), Size::new(3, 3)), 1, 0);
+        assert_eq!(tracker.row_marks(1), &[mark]);
+        assert!(tracker.row_marks(2).is_empty());
+
+        tracker.erase(&Rect::new(Pos::new(1, 0), Size::new(2, 3)), &Pos::new(1, 0));
+        assert_eq!(tracker.row_marks(1), &[mark]);
+        tracker.erase(&Rect::new(Pos::new(0, 1), Size::new(3, 1)), &Pos::new(0, 1));
+        assert_eq!(tracker.row_marks(1), &[mark]);
+        tracker.erase(&Rect::new(Pos::new(0, 1), Size::new(3, 2)), &Pos::new(0, 0));
+        assert!(tracker.row_marks(1).is_empty());
+
+        tracker.add_mark(&Pos::new(0, 0), ShellMark::PromptStart);
+        tracker.add_mark(&Pos::new(0, 2), ShellMark::PromptStart);
+        tracker.erase(&Rect::new(Pos::new(0, 2), Size::new(3, 1)), &Pos::new(1, 1));
+        assert!(tracker.row_marks(2).is_empty());
+        assert_eq!(tracker.row_marks(0).len(), 1);
+        tracker.erase(&Rect::new(Pos::new(0, 1), Size::new(3, 2)), &Pos::new(0, 0));
+        assert!(tracker.row_marks(0).is_empty());
+    }
+}
@@ -0,0 +1,176 @@
+// State layer callbacks that sit in front of the screen layer's own. Each one passes the callback
+// on to the screen layer unchanged, then updates what the VTerm tracks about the screen that
+// libvterm's cells have no room for, such as which hyperlink each cell belongs to.
+//
+// The screen layer is called outside of with_inner since it calls back into the screen callbacks,
+// which need the Inner themselves.
+
+use libc::{c_int, c_void};
+
+use super::*;
+use inner::{Inner, with_inner};
+
+pub extern "C" fn put_glyph(info: *mut ffi::VTermGlyphInfo,
+                            pos: ffi::VTermPos,
+                            inner: *mut c_void)
+                            -> c_int {
+    let glyph_pos = pos.as_pos();
+    let ret = forward(inner, |cbs, data| cbs.put_glyph.map(|f| f(info, pos, data)));
+    with_inner(inner, |inner| {
+        if ret != 0 {
+            let width = unsafe { ffi::vterm_glyph_info_width(info) } as usize;
+            inner.tracker.put_glyph(&glyph_pos, width);
+        }
+        ret
+    })
+}
+
+pub extern "C" fn move_cursor(new: ffi::VTermPos,
+                              old: ffi::VTermPos,
+                              visible: c_int,
+                              inner: *mut c_void)
+                              -> c_int {
+    forward(inner,
+            |cbs, data| cbs.move_cursor.map(|f| f(new, old, visible, data)))
+}
+
+pub extern "C" fn scroll_rect(rect: ffi::VTermRect,
+                              downward: c_int,
+                              rightward: c_int,
+                              inner: *mut c_void)
+                              -> c_int {
+    with_inner(inner, |inner| {
+        inner.tracker.start_scroll();
+        1
+    });
+    let scrolled = rect.as_rect();
+    let ret = forward(inner,
+                      |cbs, data| cbs.scroll_rect.map(|f| f(rect, downward, rightward, data)));
+    with_inner(inner, |inner| {
+        inner.tracker.scroll_rect(&scrolled, downward as isize, rightward as isize);
+        ret
+    })
+}
+
+pub extern "C" fn erase(rect: ffi::VTermRect, selective: c_int, inner: *mut c_void) -> c_int {
+    let erased = rect.as_rect();
+    let ret = forward(inner, |cbs, data| cbs.erase.map(|f| f(rect, selective, data)));
+    with_inner(inner, |inner| {
+        if !int_to_bool(selective) {
+            let mut cursor = ffi::VTermPos { row: 0, col: 0 };
+            unsafe { ffi::vterm_state_get_cursorpos(inner.state_ptr, &mut cursor) };
+            inner.tracker.erase(&erased, &cursor.as_pos());
+        }
+        ret
+    })
+}
+
+/// Called when the terminal is reset. The active hyperlink is part of the pen, so it goes too.
+pub extern "C" fn init_pen(inner: *mut c_void) -> c_int {
+    let ret = forward(inner, |cbs, data| cbs.init_pen.map(|f| f(data)));
+    with_inner(inner, |inner| {
+        inner.tracker.active_link = None;
+        ret
+    })
+}
+
+pub extern "C" fn set_pen_attr(attr: ffi::VTermAttr,
+                               val: *mut ffi::VTermValue,
+                               inner: *mut c_void)
+                               -> c_int {
+    forward(inner, |cbs, data| cbs.set_pen_attr.map(|f| f(attr, val, data)))
+}
+
+pub extern "C" fn set_term_prop(prop: ffi::VTermProp,
+                                val: *mut ffi::VTermValue,
+                                inner: *mut c_void)
+                                -> c_int {
+    let is_altscreen = match prop {
+        ffi::VTermProp::VTermPropAltscreen => true,
+        _ => false,
+    };
+    let ret = forward(inner, |cbs, data| cbs.set_term_prop.map(|f| f(prop, val, data)));
+    with_inner(inner, |inner| {
+        // The screen switches buffers whatever the screen handler returns
+        if is_altscreen {
+            let is_on = int_to_bool(unsafe { ffi::vterm_value_get_boolean(val) });
+            inner.tracker.set_altscreen(is_on);
+        }
+        ret
+    })
+}
+
+pub extern "C" fn bell(inner: *mut c_void) -> c_int {
+    forward(inner, |cbs, data| cbs.bell.map(|f| f(data)))
+}
+
+/// The screen layer moves its content up or down when the height changes, and says by how much
+/// through delta.
+pub extern "C" fn resize(rows: c_int,
+                         cols: c_int,
+                         delta: *mut ffi::VTermPos,
+                         inner: *mut c_void)
+                         -> c_int {
+    with_inner(inner, |inner| {
+        inner.tracker.start_scroll();
+        1
+    });
+    let old_row = unsafe { (*delta).row };
+    let ret = forward(inner, |cbs, data| cbs.resize.map(|f| f(rows, cols, delta, data)));
+    let shift = unsafe { (*delta).row } - old_row;
+    with_inner(inner, |inner| {
+        inner.tracker.resize(Size::new(cols as usize, rows as usize), shift as isize);
+        ret
+    })
+}
+
+pub extern "C" fn set_line_info(row: c_int,
+                                new: *const ffi::VTermLineInfo,
+                                old: *const ffi::VTermLineInfo,
+                                inner: *mut c_void)
+                                -> c_int {
+    forward(inner, |cbs, data| cbs.set_line_info.map(|f| f(row, new, old, data)))
+}
+
+/// Call one of the screen layer's state callbacks, returning 0 if it doesn't have that one.
+fn forward<F>(inner: *mut c_void, call: F) -> c_int
+    where F: FnOnce(&ffi::VTermStateCallbacks, *mut c_void) -> Option<c_int>
+{
+    let (callbacks, data) = {
+        let inner: &Inner = unsafe { &*(inner as *const Inner) };
+        (inner.screen_state_callbacks, inner.screen_state_cbdata)
+    };
+    if callbacks.is_null() {
+        return 0;
+    }
+
+    call(unsafe { &*callbacks }, data).unwrap_or(0)
+}
+
+/// Put the tracking callbacks in front of whatever state callbacks are registered now, which is
+/// the screen layer's.
+pub fn set_callbacks(state_ptr: *mut ffi::VTermState, inner: &mut Inner) {
+    inner.screen_state_callbacks = unsafe { ffi::vterm_state_get_callbacks(state_ptr) };
+    inner.screen_state_cbdata = unsafe { ffi::vterm_state_get_cbdata(state_ptr) };
+
+    inner.tracking_callbacks = Some(ffi::VTermStateCallbacks {
+        put_glyph: Some(put_glyph),
+        move_cursor: Some(move_cursor),
+        scroll_rect: Some(scroll_rect),
+        move_rect: None,
+        erase: Some(erase),
+        init_pen: Some(init_pen),
+        set_pen_attr: Some(set_pen_attr),
+        set_term_prop: Some(set_term_prop),
+        bell: Some(bell),
+        resize: Some(resize),
+        set_line_info: Some(set_line_info),
+    });
+
+    let inner_ptr: *mut c_void = inner as *mut _ as *mut c_void;
+    unsafe {
+        ffi::vterm_state_set_callbacks(state_ptr,
+                                       inner.tracking_callbacks.as_ref().unwrap(),
+                                       inner_ptr);
+    }
+}
@@ -0,0 +1,45 @@
+use std::sync::mpsc;
+
+use super::*;
+
+/// A sequence the state layer didn't understand. These are what libvterm calls unrecognised
+/// fallbacks, and can be used to implement private extensions.
+#[derive(PartialEq, Debug, Clone)]
+pub enum UnhandledSequence {
+    Control(u8),
+    Csi {
+        leader: Vec<u8>,
+        args: Vec<CsiArg>,
+        intermed: Vec<u8>,
+        command: u8,
+    },
+    /// The contents of an OSC string, without the terminator. The title and icon name commands
+    /// never end up here.
+    Osc(Vec<u8>),
+    /// The contents of a DCS string, without the terminator. Status string requests (`$q`) never
+    /// end up here.
+    Dcs(Vec<u8>),
+}
+
+impl VTerm {
+    /// Setup the vterm to send sequences the state layer didn't understand to a channel, whose
+    /// receiver end is in the unhandled_sequence_rx field. This replaces any handler given to
+    /// set_unhandled_sequence_handler.
+    pub fn unhandled_sequence_receive_events(&mut self) {
+        if self.unhandled_sequence_tx.is_none() {
+            let (tx, rx) = mpsc::channel();
+            self.unhandled_sequence_tx = Some(tx);
+            self.unhandled_sequence_rx = Some(rx);
+        }
+
+        let tx = self.unhandled_sequence_tx.as_ref().unwrap().clone();
+        self.set_unhandled_sequence_handler(Box::new(ChannelUnhandledSequenceHandler::new(tx)));
+    }
+
+    /// Call the handler synchronously for every sequence the state layer didn't understand. The
+    /// state and screen layers share one set of fallbacks in libvterm, so this covers both. OSC
+    /// commands this crate handles itself, like OSC 52, aren't passed on.
+    pub fn set_unhandled_sequence_handler(&mut self, handler: Box<UnhandledSequenceHandler>) {
+        self.inner.unhandled_sequence_handler = Some(handler);
+    }
+}
@@ -0,0 +1,79 @@
+#![allow(unused_variables)]
+
+use libc::{c_char, c_int, c_long, c_uchar, c_void, size_t};
+use std::slice;
+
+use super::*;
+use inner::{Inner, with_inner};
+use parser_callbacks::{bytes_from_ptr, bytes_from_c_str};
+
+pub extern "C" fn control(control: c_uchar, inner: *mut c_void) -> c_int {
+    with_handler(inner, || UnhandledSequence::Control(control as u8))
+}
+
+pub extern "C" fn csi(leader: *const c_char,
+                      args: *const c_long,
+                      argcount: c_int,
+                      intermed: *const c_char,
+                      command: c_char,
+                      inner: *mut c_void)
+                      -> c_int {
+    with_handler(inner, || {
+        let args = unsafe { slice::from_raw_parts(args, argcount as usize) };
+        UnhandledSequence::Csi {
+            leader: unsafe { bytes_from_c_str(leader) },
+            args: args.iter().map(|&arg| CsiArg::from_raw(arg)).collect(),
+            intermed: unsafe { bytes_from_c_str(intermed) },
+            command: command as u8,
+        }
+    })
+}
+
+pub extern "C" fn osc(command: *const c_char, cmdlen: size_t, inner: *mut c_void) -> c_int {
+    let bytes = unsafe { bytes_from_ptr(command, cmdlen) };
+    let is_handled = with_inner(inner, |inner| if ::osc::handle(inner, bytes) { 1 } else { 0 });
+    if is_handled == 1 {
+        return 1;
+    }
+
+    with_handler(inner, || UnhandledSequence::Osc(bytes.to_vec()))
+}
+
+pub extern "C" fn dcs(command: *const c_char, cmdlen: size_t, inner: *mut c_void) -> c_int {
+    with_handler(inner, || {
+        let bytes = unsafe { bytes_from_ptr(command, cmdlen) };
+        UnhandledSequence::Dcs(bytes.to_vec())
+    })
+}
+
+/// Give libvterm the fallbacks, with the Inner as user data. They're always set so the OSC
+/// commands this crate handles itself work whether or not there's an UnhandledSequenceHandler.
+pub fn set_callbacks(state_ptr: *mut ffi::VTermState, inner: &mut Inner) {
+    let mut callbacks: ffi::VTermParserCallbacks = Default::default();
+    callbacks.control = Some(control);
+    callbacks.csi = Some(csi);
+    callbacks.osc = Some(osc);
+    callbacks.dcs = Some(dcs);
+
+    inner.unhandled_callbacks = Some(callbacks);
+
+    unsafe {
+        let inner_ptr: *mut c_void = inner as *mut _ as *mut c_void;
+        ffi::vterm_state_set_unrecognised_fallbacks(state_ptr,
+                                                    inner.unhandled_callbacks.as_ref().unwrap(),
+                                                    inner_ptr);
+    }
+}
+
+/// Give the sequence built by the closure to the vterms handler, if there is one. Panics are caught
+/// by with_inner.
+fn with_handler<F>(inner: *mut c_void, build_seq: F) -> c_int
+    where F: FnOnce() -> UnhandledSequence
+{
+    with_inner(inner, |inner| {
+        match inner.unhandled_sequence_handler.as_mut() {
+            Some(handler) => if handler.handle(build_seq()) { 1 } else { 0 },
+            None => 0,
+        }
+    })
+}
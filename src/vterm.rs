@@ -1,49 +1,86 @@
-use libc::{c_int, size_t};
+use libc::{c_int, c_char, size_t};
 use std::sync::mpsc;
 use std::ptr::Unique;
 use std::io::prelude::*;
 
 use super::*;
+use inner::Inner;
 
 pub struct VTerm {
     pub ptr: Unique<ffi::VTerm>,
 
-    pub screen_callbacks: Option<ffi::VTermScreenCallbacks>,
+    /// Data shared with libvterm callbacks. Boxed so the VTerm can be moved after subscribing to
+    /// events.
+    pub inner: Box<Inner>,
+
+    pub parser_event_rx: Option<mpsc::Receiver<ParserEvent>>,
+
     pub screen_event_rx: Option<mpsc::Receiver<ScreenEvent>>,
+    /// Kept so calling screen_receive_events again reuses the same channel.
     pub screen_event_tx: Option<mpsc::Sender<ScreenEvent>>,
     pub screen_ptr: Unique<ffi::VTermScreen>,
 
-    pub state_callbacks: Option<ffi::VTermStateCallbacks>,
     pub state_event_rx: Option<mpsc::Receiver<StateEvent>>,
+    /// Kept so calling state_receive_events again reuses the same channel.
     pub state_event_tx: Option<mpsc::Sender<StateEvent>>,
     pub state_ptr: Unique<ffi::VTermState>,
+
+    pub unhandled_sequence_rx: Option<mpsc::Receiver<UnhandledSequence>>,
+    /// Kept so calling unhandled_sequence_receive_events again reuses the same channel.
+    pub unhandled_sequence_tx: Option<mpsc::Sender<UnhandledSequence>>,
 }
 
 impl VTerm {
+    /// Panics if libvterm can't create the terminal. See try_new.
     pub fn new(size: &Size) -> VTerm {
-        // TODO how to detect error?
-        let mut vterm_ptr = unsafe {
-            Unique::new(ffi::vterm_new(size.height as c_int, size.width as c_int))
-        };
+        match VTerm::try_new(size) {
+            Ok(vterm) => vterm,
+            Err(e) => panic!("{}", e),
+        }
+    }
+
+    pub fn try_new(size: &Size) -> Result<VTerm, Error> {
+        let raw_ptr = unsafe { ffi::vterm_new(size.height as c_int, size.width as c_int) };
+        if raw_ptr.is_null() {
+            return Err(Error::NewFailed);
+        }
+
+        let mut vterm_ptr = unsafe { Unique::new(raw_ptr) };
         let screen_ptr = unsafe { Unique::new(ffi::vterm_obtain_screen(vterm_ptr.get_mut())) };
-        let state_ptr = unsafe { Unique::new(ffi::vterm_obtain_state(vterm_ptr.get_mut())) };
+        let raw_state_ptr = unsafe { ffi::vterm_obtain_state(vterm_ptr.get_mut()) };
+        let state_ptr = unsafe { Unique::new(raw_state_ptr) };
 
         let mut vterm = VTerm {
             ptr: vterm_ptr,
-            screen_callbacks: None,
+            inner: Box::new(Inner::new(raw_ptr, raw_state_ptr)),
+
+            parser_event_rx: None,
+
             screen_event_rx: None,
             screen_event_tx: None,
             screen_ptr: screen_ptr,
 
-            state_callbacks: None,
             state_event_rx: None,
             state_event_tx: None,
             state_ptr: state_ptr,
+
+            unhandled_sequence_rx: None,
+            unhandled_sequence_tx: None,
         };
 
+        // The scrollback callbacks are always registered so no lines are missed
+        ::screen_callbacks::set_callbacks(unsafe { vterm.screen_ptr.get_mut() },
+                                          &mut vterm.inner,
+                                          &ScreenCallbacksConfig::none());
+        ::unhandled_callbacks::set_callbacks(unsafe { vterm.state_ptr.get_mut() },
+                                             &mut vterm.inner);
+        vterm.inner.tracker.resize(size.clone(), 0);
+        vterm.inner.palette_index = PaletteIndex::new(unsafe { vterm.state_ptr.get() });
+        ::tracking_callbacks::set_callbacks(unsafe { vterm.state_ptr.get_mut() },
+                                            &mut vterm.inner);
         vterm.screen_reset(true);
 
-        vterm
+        Ok(vterm)
     }
 
     pub fn get_size(&self) -> Size {
@@ -73,9 +110,35 @@ impl VTerm {
     pub fn set_utf8(&mut self, is_utf8: bool) {
         unsafe { ffi::vterm_set_utf8(self.ptr.get_mut(), super::bool_to_int(is_utf8)) }
     }
+
+    /// Returns and clears the error from a callback that panicked while handling input, if any.
+    /// `write` can't return it since libvterm has used the bytes by then, so check this or
+    /// `flush` after writing.
+    pub fn take_error(&mut self) -> Option<Error> {
+        self.inner.take_error()
+    }
+
+    /// Returns the number of bytes waiting in the output buffer, which can be had by using the
+    /// Read trait.
+    pub fn output_pending(&self) -> usize {
+        unsafe { ffi::vterm_output_get_buffer_current(self.ptr.get()) as usize }
+    }
+}
+
+impl Read for VTerm {
+    fn read(&mut self, buf: &mut [u8]) -> ::std::io::Result<usize> {
+        let size = unsafe {
+            ffi::vterm_output_read(self.ptr.get_mut(),
+                                   buf.as_mut_ptr() as *mut c_char,
+                                   buf.len() as size_t) as usize
+        };
+        Ok(size)
+    }
 }
 
 impl Write for VTerm {
+    /// Always takes the whole buffer. A callback that panics doesn't make this fail, since the
+    /// bytes were used anyway; the panic is reported by `flush` or `take_error` instead.
     fn write(&mut self, buf: &[u8]) -> ::std::io::Result<usize> {
         let size = unsafe {
             ffi::vterm_input_write(self.ptr.get_mut(), buf.as_ptr(), buf.len() as size_t) as usize
@@ -83,9 +146,13 @@ impl Write for VTerm {
         Ok(size)
     }
 
+    /// Returns an error if a callback panicked while handling input since the last check.
     fn flush(&mut self) -> ::std::io::Result<()> {
         self.screen_flush_damage();
-        Ok(())
+        match self.take_error() {
+            Some(err) => Err(::std::io::Error::new(::std::io::ErrorKind::Other, err)),
+            None => Ok(()),
+        }
     }
 }
 
@@ -109,6 +176,15 @@ mod tests {
         drop(vterm);
     }
 
+    #[test]
+    fn vterm_can_try_new() {
+        let vterm = VTerm::try_new(&Size {
+            height: 2,
+            width: 2,
+        });
+        assert!(vterm.is_ok());
+    }
+
     #[test]
     fn vterm_can_get_size() {
         let vterm: VTerm = VTerm::new(&Size {
@@ -157,4 +233,39 @@ mod tests {
         assert!(result.is_ok());
         assert_eq!(4, result.unwrap());
     }
+
+    #[test]
+    fn vterm_flush_returns_callback_errors() {
+        let mut vterm: VTerm = VTerm::new(&Size {
+            height: 2,
+            width: 2,
+        });
+        vterm.inner.error = Some(Error::CallbackPanicked("boom".to_string()));
+        assert_eq!(vterm.write(b"a").unwrap(), 1);
+        assert!(vterm.flush().is_err());
+        assert!(vterm.flush().is_ok());
+    }
+
+    #[test]
+    fn vterm_can_read() {
+        let mut vterm: VTerm = VTerm::new(&Size {
+            height: 2,
+            width: 2,
+        });
+        assert_eq!(0, vterm.output_pending());
+
+        // DA
+        vterm.write(b"\x1b[c").unwrap();
+        assert_eq!(7, vterm.output_pending());
+
+        let mut buf = [0u8; 4];
+        assert_eq!(4, vterm.read(&mut buf).unwrap());
+        assert_eq!(&buf, b"\x1b[?1");
+        assert_eq!(3, vterm.output_pending());
+
+        let mut rest = vec![];
+        vterm.read_to_end(&mut rest).unwrap();
+        assert_eq!(rest, b";2c");
+        assert_eq!(0, vterm.output_pending());
+    }
 }
@@ -0,0 +1,124 @@
+use std::io::prelude::*;
+use vterm_sys::*;
+
+fn new_vterm(height: usize, width: usize) -> VTerm {
+    VTerm::new(&Size {
+        height: height,
+        width: width,
+    })
+}
+
+fn links(cells: &[ScreenCell]) -> Vec<Option<LinkId>> {
+    cells.iter().map(|cell| cell.link).collect()
+}
+
+#[test]
+fn hyperlink_is_attached_to_cells_written_while_active() {
+    let mut vterm = new_vterm(2, 6);
+    vterm.write(b"a\x1b]8;id=x:foo=bar;http://example.com\x1b\\bc\x1b]8;;\x1b\\d").unwrap();
+
+    let id = vterm.screen_get_cell(&Pos::new(1, 0)).link.unwrap();
+    let row = vterm.screen_get_cells_in_rect(&Rect::new(Pos::new(0, 0), Size::new(5, 1)));
+    assert_eq!(links(&row), vec![None, Some(id), Some(id), None, None]);
+
+    let link = vterm.hyperlink(id).unwrap();
+    assert_eq!(link.uri, "http://example.com");
+    assert_eq!(link.id(), Some("x"));
+    assert_eq!(link.param("foo"), Some("bar"));
+    assert_eq!(vterm.active_hyperlink(), None);
+}
+
+#[test]
+fn hyperlink_with_a_long_uri_can_be_split_across_writes() {
+    let mut vterm = new_vterm(2, 6);
+    let uri = format!("http://example.com/{}", (0..100).map(|_| "a").collect::<String>());
+    let sequence = format!("\x1b]8;;{}\x1b\\b", uri);
+
+    let (start, end) = sequence.as_bytes().split_at(20);
+    vterm.write(start).unwrap();
+    vterm.write(end).unwrap();
+
+    let id = vterm.screen_get_cell(&Pos::new(0, 0)).link.unwrap();
+    assert_eq!(vterm.hyperlink(id).unwrap().uri, uri);
+}
+
+#[test]
+fn hyperlink_ids_are_reused_for_the_same_link() {
+    let mut vterm = new_vterm(2, 6);
+    vterm.write(b"\x1b]8;;http://a\x1b\\a\x1b]8;;http://b\x1b\\b\x1b]8;;http://a\x1b\\c").unwrap();
+
+    let row = vterm.screen_get_cells_in_rect(&Rect::new(Pos::new(0, 0), Size::new(3, 1)));
+    assert_eq!(row[0].link, row[2].link);
+    assert!(row[0].link != row[1].link);
+}
+
+#[test]
+fn hyperlink_ids_are_reclaimed_once_unused() {
+    let mut vterm = new_vterm(2, 6);
+    vterm.set_scrollback_limit(1);
+
+    vterm.write(b"\x1b]8;;http://0\x1b\\a\r\n").unwrap();
+    let first = vterm.screen_get_cell(&Pos::new(0, 0)).link.unwrap();
+    for i in 1..1000 {
+        vterm.write(format!("\x1b]8;;http://{}\x1b\\a\r\n", i).as_bytes()).unwrap();
+    }
+
+    assert_eq!(vterm.hyperlink(first), None);
+    let last = vterm.screen_get_cell(&Pos::new(0, 0)).link.unwrap();
+    assert_eq!(vterm.hyperlink(last).unwrap().uri, "http://999");
+    let pushed = vterm.scrollback().line(0).unwrap()[0].link.unwrap();
+    assert_eq!(vterm.hyperlink(pushed).unwrap().uri, "http://998");
+}
+
+#[test]
+fn hyperlink_moves_with_scrolled_text() {
+    let mut vterm = new_vterm(2, 3);
+    vterm.write(b"\x1b]8;;http://a\x1b\\one\x1b]8;;\x1b\\\r\ntwo\r\nsix").unwrap();
+
+    assert_eq!(vterm.active_hyperlink(), None);
+
+    let line = vterm.scrollback().line(0).unwrap();
+    assert!(line[0].link.is_some());
+    assert_eq!(vterm.hyperlink(line[0].link.unwrap()).unwrap().uri, "http://a");
+
+    let row = vterm.screen_get_cells_in_rect(&Rect::new(Pos::new(0, 0), Size::new(3, 2)));
+    assert_eq!(links(&row), vec![None; 6]);
+
+    // The line comes back on the screen with its link
+    vterm.set_size(&Size {
+        height: 3,
+        width: 3,
+    });
+    let row = vterm.screen_get_cells_in_rect(&Rect::new(Pos::new(0, 0), Size::new(3, 1)));
+    assert!(links(&row).iter().all(|link| link.is_some()));
+}
+
+#[test]
+fn hyperlink_is_gone_from_erased_cells() {
+    let mut vterm = new_vterm(2, 3);
+    vterm.write(b"\x1b]8;;http://a\x1b\\ab\x1b[2J").unwrap();
+
+    assert_eq!(vterm.screen_get_cell(&Pos::new(0, 0)).link, None);
+    assert!(vterm.active_hyperlink().is_some());
+}
+
+#[test]
+fn hyperlink_is_cleared_by_a_reset() {
+    let mut vterm = new_vterm(2, 3);
+    vterm.write(b"\x1b]8;;http://a\x1b\\a\x1bca").unwrap();
+
+    assert_eq!(vterm.active_hyperlink(), None);
+    assert_eq!(vterm.screen_get_cell(&Pos::new(0, 0)).link, None);
+}
+
+#[test]
+fn hyperlink_on_the_altscreen_stays_there() {
+    let mut vterm = new_vterm(2, 3);
+    vterm.screen_enable_altscreen(true);
+    vterm.write(b"ab\x1b[?1049h\x1b[H\x1b]8;;http://a\x1b\\ab").unwrap();
+    assert!(vterm.screen_get_cell(&Pos::new(0, 0)).link.is_some());
+
+    vterm.write(b"\x1b[?1049l").unwrap();
+    assert_eq!(vterm.screen_get_cell(&Pos::new(0, 0)).link, None);
+    assert_eq!(vterm.screen_get_cell(&Pos::new(1, 0)).link, None);
+}
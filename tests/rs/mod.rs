@@ -1,3 +1,9 @@
+mod hyperlink;
+mod parser_callbacks;
 mod screen;
 mod screen_callbacks;
+mod scrollback;
+mod shell_integration;
+mod state;
 mod state_callbacks;
+mod unhandled_callbacks;
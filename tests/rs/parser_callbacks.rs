@@ -0,0 +1,107 @@
+use std::io::prelude::*;
+use vterm_sys::*;
+
+fn parse(input: &[u8]) -> Vec<ParserEvent> {
+    let mut vterm: VTerm = VTerm::new(&Size {
+        height: 2,
+        width: 2,
+    });
+    vterm.parser_receive_events();
+    vterm.write(input).unwrap();
+
+    let rx = vterm.parser_event_rx.take().unwrap();
+    let mut events = vec![];
+    while let Ok(e) = rx.try_recv() {
+        events.push(e);
+    }
+    events
+}
+
+#[test]
+fn parser_can_generate_text_and_control_events() {
+    assert_eq!(parse(b"ab\r\ncd"),
+               vec![ParserEvent::Text(b"ab".to_vec()),
+                    ParserEvent::Control(b'\r'),
+                    ParserEvent::Control(b'\n'),
+                    ParserEvent::Text(b"cd".to_vec())]);
+}
+
+#[test]
+fn parser_can_generate_escape_events() {
+    assert_eq!(parse(b"\x1b(0\x1b="),
+               vec![ParserEvent::Escape(b"(0".to_vec()), ParserEvent::Escape(b"=".to_vec())]);
+}
+
+#[test]
+fn parser_can_generate_csi_events() {
+    assert_eq!(parse(b"\x1b[?25h"),
+               vec![ParserEvent::Csi {
+                        leader: b"?".to_vec(),
+                        args: vec![CsiArg::new(Some(25), false)],
+                        intermed: vec![],
+                        command: b'h',
+                    }]);
+
+    assert_eq!(parse(b"\x1b[2 q"),
+               vec![ParserEvent::Csi {
+                        leader: vec![],
+                        args: vec![CsiArg::new(Some(2), false)],
+                        intermed: b" ".to_vec(),
+                        command: b'q',
+                    }]);
+}
+
+#[test]
+fn parser_decodes_missing_csi_args_and_subparameters() {
+    assert_eq!(parse(b"\x1b[;5H"),
+               vec![ParserEvent::Csi {
+                        leader: vec![],
+                        args: vec![CsiArg::new(None, false), CsiArg::new(Some(5), false)],
+                        intermed: vec![],
+                        command: b'H',
+                    }]);
+
+    assert_eq!(parse(b"\x1b[1;2:3:4;5a"),
+               vec![ParserEvent::Csi {
+                        leader: vec![],
+                        args: vec![CsiArg::new(Some(1), false),
+                                   CsiArg::new(Some(2), true),
+                                   CsiArg::new(Some(3), true),
+                                   CsiArg::new(Some(4), false),
+                                   CsiArg::new(Some(5), false)],
+                        intermed: vec![],
+                        command: b'a',
+                    }]);
+
+    assert_eq!(parse(b"\x1b[38:2::10:20:30m"),
+               vec![ParserEvent::Csi {
+                        leader: vec![],
+                        args: vec![CsiArg::new(Some(38), true),
+                                   CsiArg::new(Some(2), true),
+                                   CsiArg::new(None, true),
+                                   CsiArg::new(Some(10), true),
+                                   CsiArg::new(Some(20), true),
+                                   CsiArg::new(Some(30), false)],
+                        intermed: vec![],
+                        command: b'm',
+                    }]);
+}
+
+#[test]
+fn parser_can_generate_osc_and_dcs_events() {
+    assert_eq!(parse(b"\x1b]2;title\x07\x1bP$qm\x1b\\"),
+               vec![ParserEvent::Osc(b"2;title".to_vec()), ParserEvent::Dcs(b"$qm".to_vec())]);
+}
+
+#[test]
+fn parser_events_replace_the_state_layer() {
+    let mut vterm: VTerm = VTerm::new(&Size {
+        height: 1,
+        width: 2,
+    });
+    vterm.parser_receive_events();
+    vterm.write(b"ab").unwrap();
+
+    assert_eq!(vterm.screen_get_text_lossy(&Rect::new(Pos::new(0, 0), Size::new(2, 1))),
+               "");
+}
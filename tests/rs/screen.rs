@@ -14,7 +14,7 @@ fn screen_get_cell_returns_cell() {
     vterm.flush().unwrap();
 
     let cell = vterm.screen_get_cell(&Pos::new(0, 0));
-    assert_eq!(cell.chars[0], b'a');
+    assert_eq!(cell.chars[0], 'a');
 }
 
 #[test]
@@ -109,7 +109,7 @@ fn screen_get_cells_in_rect_returns_cells() {
     vterm.flush().unwrap();
 
     let cells = vterm.screen_get_cells_in_rect(&Rect::new(Pos::new(0, 0), Size::new(2,2)));
-    assert_eq!(cells.iter().map(|c| c.chars[0]).collect::<Vec<u8>>(), b"abcd");
+    assert_eq!(cells.iter().map(|c| c.text()).collect::<String>(), "abcd");
 }
 
 #[test]
@@ -121,3 +121,224 @@ fn screen_get_cells_in_rect_panics_if_out_of_bounds() {
     });
     vterm.screen_get_cells_in_rect(&Rect::new(Pos::new(1, 0), Size::new(2,2)));
 }
+
+#[test]
+fn try_screen_get_cell_returns_error_if_out_of_bounds() {
+    let vterm: VTerm = VTerm::new(&Size {
+        height: 1,
+        width: 1,
+    });
+    assert_eq!(vterm.try_screen_get_cell(&Pos::new(1, 0)),
+               Err(Error::PosOutOfBounds {
+                   pos: Pos::new(1, 0),
+                   size: Size::new(1, 1),
+               }));
+}
+
+#[test]
+fn try_screen_get_text_returns_error_if_rect_is_out_of_bounds() {
+    let vterm: VTerm = VTerm::new(&Size {
+        height: 1,
+        width: 1,
+    });
+    let rect = Rect::new(Pos::new(1, 0), Size::new(1, 2));
+    assert_eq!(vterm.try_screen_get_text(&rect),
+               Err(Error::RectOutOfBounds {
+                   rect: rect.clone(),
+                   size: Size::new(1, 1),
+               }));
+    assert!(vterm.try_screen_get_text_lossy(&rect).is_err());
+}
+
+#[test]
+fn try_screen_get_cells_in_rect_returns_error_if_out_of_bounds() {
+    let vterm: VTerm = VTerm::new(&Size {
+        height: 1,
+        width: 1,
+    });
+    assert!(vterm.try_screen_get_cells_in_rect(&Rect::new(Pos::new(1, 0), Size::new(2,2))).is_err());
+}
+
+#[test]
+fn screen_cells_know_if_they_are_protected() {
+    let mut vterm: VTerm = VTerm::new(&Size {
+        height: 1,
+        width: 4,
+    });
+    // DECSCA on for "ab" and off for "cd"
+    vterm.write(b"\x1b[1\"qab\x1b[0\"qcd").unwrap();
+
+    let cells = vterm.screen_get_cells_in_rect(&Rect::new(Pos::new(0, 0), Size::new(4, 1)));
+    let protected: Vec<bool> = cells.iter().map(|cell| cell.protected).collect();
+    assert_eq!(protected, vec![true, true, false, false]);
+
+    // DECSED leaves protected cells alone
+    vterm.write(b"\x1b[?2J").unwrap();
+    let cells = vterm.screen_get_cells_in_rect(&Rect::new(Pos::new(0, 0), Size::new(4, 1)));
+    let chars: Vec<usize> = cells.iter().map(|cell| cell.chars.len()).collect();
+    assert!(chars[0] > 0 && chars[1] > 0);
+    assert_eq!(&chars[2..], &[0, 0]);
+
+    // ED doesn't
+    vterm.write(b"\x1b[2J").unwrap();
+    assert!(!vterm.screen_get_cell(&Pos::new(0, 0)).protected);
+    assert_eq!(vterm.screen_get_cell(&Pos::new(0, 0)).chars.len(), 0);
+}
+
+#[test]
+fn screen_cells_have_their_text() {
+    let mut vterm: VTerm = VTerm::new(&Size {
+        height: 1,
+        width: 5,
+    });
+    vterm.set_utf8(true);
+    // A wide character, then an e with a combining acute accent
+    vterm.write("日e\u{301}x".as_bytes()).unwrap();
+
+    let cells = vterm.screen_get_cells_in_rect(&Rect::new(Pos::new(0, 0), Size::new(5, 1)));
+    assert_eq!(cells[0].text(), "日");
+    assert_eq!(cells[0].width, 2);
+    assert!(!cells[0].is_wide_continuation());
+
+    assert_eq!(cells[1].text(), "");
+    assert!(cells[1].is_wide_continuation());
+
+    assert_eq!(cells[2].chars, vec!['e', '\u{301}']);
+    assert_eq!(cells[2].text(), "e\u{301}");
+    assert_eq!(cells[3].text(), "x");
+
+    assert_eq!(cells[4].text(), "");
+    assert!(!cells[4].is_wide_continuation());
+}
+
+#[test]
+fn screen_wide_characters_come_back_from_the_scrollback() {
+    let mut vterm: VTerm = VTerm::new(&Size {
+        height: 2,
+        width: 4,
+    });
+    vterm.set_utf8(true);
+    vterm.write("日本\r\nx\r\ny".as_bytes()).unwrap();
+
+    let line = vterm.scrollback().line(0).unwrap().to_vec();
+    assert_eq!(line[0].text(), "日");
+    assert!(line[1].is_wide_continuation());
+
+    vterm.set_size(&Size {
+        height: 3,
+        width: 4,
+    });
+    let cells = vterm.screen_get_cells_in_rect(&Rect::new(Pos::new(0, 0), Size::new(4, 1)));
+    let text: Vec<String> = cells.iter().map(|cell| cell.text()).collect();
+    assert_eq!(text, vec!["日", "", "本", ""]);
+    assert!(cells[1].is_wide_continuation() && cells[3].is_wide_continuation());
+    assert_eq!(cells[0].width, 2);
+}
+
+#[test]
+fn screen_wide_character_cut_off_by_a_narrower_screen_comes_back_blank() {
+    let mut vterm: VTerm = VTerm::new(&Size {
+        height: 2,
+        width: 4,
+    });
+    vterm.set_utf8(true);
+    vterm.write("日本\r\nx\r\ny".as_bytes()).unwrap();
+
+    vterm.set_size(&Size {
+        height: 3,
+        width: 3,
+    });
+    let cells = vterm.screen_get_cells_in_rect(&Rect::new(Pos::new(0, 0), Size::new(3, 1)));
+    let text: Vec<String> = cells.iter().map(|cell| cell.text()).collect();
+    assert_eq!(text, vec!["日", "", ""]);
+    assert_eq!(cells[2].width, 1);
+}
+
+#[test]
+fn screen_cell_full_of_combining_characters_comes_back_from_the_scrollback() {
+    let mut vterm: VTerm = VTerm::new(&Size {
+        height: 2,
+        width: 4,
+    });
+    vterm.set_utf8(true);
+    vterm.write("e\u{301}\u{302}\u{303}\u{304}\u{305}\u{306}x\r\ny\r\nz".as_bytes()).unwrap();
+    let chars = vterm.scrollback().line(0).unwrap()[0].chars.clone();
+    assert_eq!(chars.len(), ffi::VTERM_MAX_CHARS_PER_CELL);
+
+    vterm.set_size(&Size {
+        height: 3,
+        width: 4,
+    });
+    let cells = vterm.screen_get_cells_in_rect(&Rect::new(Pos::new(0, 0), Size::new(4, 1)));
+    assert_eq!(cells[0].chars[..], chars[..ffi::VTERM_MAX_CHARS_PER_CELL - 1]);
+    assert_eq!(cells[1].text(), "x");
+}
+
+#[test]
+fn screen_snapshot_matches_the_cells() {
+    let mut vterm: VTerm = VTerm::new(&Size {
+        height: 3,
+        width: 5,
+    });
+    vterm.set_utf8(true);
+    vterm.write("\x1b[1;31mab\x1b[m\r\n\x1b]8;;http://a\x1b\\日\x1b]8;;\x1b\\\x1b[1\"qe\u{301}"
+                    .as_bytes())
+        .unwrap();
+
+    let snapshot = vterm.screen_snapshot();
+    assert_eq!(snapshot.size, vterm.get_size());
+    assert_eq!(snapshot.cursor, vterm.cursor_pos());
+    assert_eq!(snapshot.cursor, Pos::new(3, 1));
+
+    let rect = Rect::new(Pos::new(0, 0), vterm.get_size());
+    assert_eq!(snapshot.cells, vterm.screen_get_cells_in_rect(&rect));
+    assert!(snapshot.cell(&Pos::new(0, 1)).unwrap().link.is_some());
+    assert!(snapshot.cell(&Pos::new(2, 1)).unwrap().protected);
+    assert_eq!(snapshot.row(1).unwrap()[2].text(), "e\u{301}");
+    assert_eq!(snapshot.cell(&Pos::new(5, 0)), None);
+    assert_eq!(snapshot.row(3), None);
+}
+
+#[test]
+fn screen_snapshot_can_be_refilled() {
+    let mut vterm: VTerm = VTerm::new(&Size {
+        height: 2,
+        width: 2,
+    });
+    let mut snapshot = vterm.screen_snapshot();
+    assert_eq!(snapshot.cells.len(), 4);
+
+    vterm.set_size(&Size {
+        height: 3,
+        width: 4,
+    });
+    vterm.write(b"xy").unwrap();
+    vterm.screen_snapshot_into(&mut snapshot);
+
+    assert_eq!(snapshot.size, Size::new(4, 3));
+    assert_eq!(snapshot.cells.len(), 12);
+    assert_eq!(snapshot.row(0).unwrap()[1].text(), "y");
+    assert_eq!(snapshot.cursor, Pos::new(2, 0));
+}
+
+#[test]
+fn screen_snapshot_refill_leaves_nothing_from_before() {
+    let mut vterm: VTerm = VTerm::new(&Size {
+        height: 2,
+        width: 6,
+    });
+    vterm.set_utf8(true);
+    vterm.write("\x1b]8;;http://a\x07\x1b[1;31;48;2;1;2;3m\x1b[1\"q日e\u{301}".as_bytes())
+        .unwrap();
+    let mut snapshot = vterm.screen_snapshot();
+    assert!(snapshot.cell(&Pos::new(1, 0)).unwrap().is_wide_continuation());
+
+    vterm.write(b"\x1b]8;;\x07\x1b[0m\x1b[0\"q\x1b[2J\x1b[Hab").unwrap();
+    vterm.screen_snapshot_into(&mut snapshot);
+
+    assert_eq!(snapshot.cells, vterm.screen_snapshot().cells);
+    assert_eq!(snapshot.cells[0].text(), "a");
+    let blank = &snapshot.cells[2];
+    assert!(blank.chars.is_empty() && blank.width == 1);
+    assert!(!blank.attrs.bold && blank.link.is_none() && !blank.protected);
+}
@@ -1,6 +1,8 @@
 use std::io::prelude::*;
 use vterm_sys::*;
 use std::sync::mpsc::Receiver;
+use std::rc::Rc;
+use std::cell::RefCell;
 use term::terminfo::TermInfo;
 use ::support::CapBuilder;
 
@@ -136,6 +138,461 @@ fn screen_can_generate_cursor_blink_events() {
     assert_eq!(event.is_on, false);
 }
 
+fn term_prop_vterm() -> VTerm {
+    let mut vterm: VTerm = VTerm::new(&Size {
+        height: 2,
+        width: 2,
+    });
+    vterm.screen_receive_events(&ScreenCallbacksConfig::all());
+    vterm
+}
+
+#[test]
+fn screen_alt_screen_events_have_real_values() {
+    let mut vterm = term_prop_vterm();
+    vterm.screen_enable_altscreen(true);
+
+    vterm.write(b"\x1b[?1049h").unwrap();
+    let rx = vterm.screen_event_rx.take().unwrap();
+    assert_eq!(try_recv_alt_screen_event(&rx), Some(AltScreenEvent { is_on: true }));
+
+    vterm.write(b"\x1b[?1049l").unwrap();
+    assert_eq!(try_recv_alt_screen_event(&rx), Some(AltScreenEvent { is_on: false }));
+}
+
+#[test]
+fn screen_cursor_visible_events_have_real_values() {
+    let mut vterm = term_prop_vterm();
+
+    vterm.write(b"\x1b[?25l").unwrap();
+    let rx = vterm.screen_event_rx.take().unwrap();
+    assert_eq!(try_recv_cursor_visible_event(&rx),
+               Some(CursorVisibleEvent { is_on: false }));
+
+    vterm.write(b"\x1b[?25h").unwrap();
+    assert_eq!(try_recv_cursor_visible_event(&rx),
+               Some(CursorVisibleEvent { is_on: true }));
+}
+
+#[test]
+fn screen_cursor_shape_events_have_real_values() {
+    let mut vterm = term_prop_vterm();
+    let rx = vterm.screen_event_rx.take().unwrap();
+
+    // DECSCUSR
+    for &(seq, ref shape) in &[(&b"\x1b[4 q"[..], CursorShape::Underline),
+                               (&b"\x1b[6 q"[..], CursorShape::BarLeft),
+                               (&b"\x1b[2 q"[..], CursorShape::Block)] {
+        vterm.write(seq).unwrap();
+        assert_eq!(try_recv_cursor_shape_event(&rx),
+                   Some(CursorShapeEvent { shape: shape.clone() }));
+    }
+}
+
+#[test]
+fn screen_title_events_have_real_values() {
+    let mut vterm = term_prop_vterm();
+
+    vterm.write(b"\x1b]2;my title\x07").unwrap();
+    let rx = vterm.screen_event_rx.take().unwrap();
+    assert_eq!(try_recv_title_event(&rx),
+               Some(TitleEvent { title: "my title".to_string() }));
+}
+
+#[test]
+fn screen_icon_name_events_have_real_values() {
+    let mut vterm = term_prop_vterm();
+
+    vterm.write(b"\x1b]1;my icon\x07").unwrap();
+    let rx = vterm.screen_event_rx.take().unwrap();
+    assert_eq!(try_recv_icon_name_event(&rx),
+               Some(IconNameEvent { name: "my icon".to_string() }));
+}
+
+#[test]
+fn screen_reverse_events_have_real_values() {
+    let mut vterm = term_prop_vterm();
+
+    // DECSCNM
+    vterm.write(b"\x1b[?5h").unwrap();
+    let rx = vterm.screen_event_rx.take().unwrap();
+    assert_eq!(try_recv_reverse_event(&rx), Some(ReverseEvent { is_on: true }));
+
+    vterm.write(b"\x1b[?5l").unwrap();
+    assert_eq!(try_recv_reverse_event(&rx), Some(ReverseEvent { is_on: false }));
+}
+
+#[test]
+fn screen_mouse_events_have_real_values() {
+    let mut vterm = term_prop_vterm();
+    let rx = vterm.screen_event_rx.take().unwrap();
+
+    for &(seq, ref mode) in &[(&b"\x1b[?1000h"[..], MouseMode::Click),
+                              (&b"\x1b[?1002h"[..], MouseMode::Drag),
+                              (&b"\x1b[?1003h"[..], MouseMode::Move),
+                              (&b"\x1b[?1003l"[..], MouseMode::None)] {
+        vterm.write(seq).unwrap();
+        assert_eq!(try_recv_mouse_event(&rx), Some(MouseEvent { mode: mode.clone() }));
+    }
+}
+
+#[test]
+fn screen_term_props_can_be_set_directly() {
+    let mut vterm = term_prop_vterm();
+    vterm.screen_enable_altscreen(true);
+    let rx = vterm.screen_event_rx.take().unwrap();
+
+    assert!(vterm.set_cursor_visible(false));
+    assert_eq!(try_recv_cursor_visible_event(&rx),
+               Some(CursorVisibleEvent { is_on: false }));
+
+    assert!(vterm.set_cursor_blink(false));
+    assert_eq!(try_recv_cursor_blink_event(&rx),
+               Some(CursorBlinkEvent { is_on: false }));
+
+    assert!(vterm.set_cursor_shape(CursorShape::BarLeft));
+    assert_eq!(try_recv_cursor_shape_event(&rx),
+               Some(CursorShapeEvent { shape: CursorShape::BarLeft }));
+
+    assert!(vterm.set_title("set directly"));
+    assert_eq!(try_recv_title_event(&rx),
+               Some(TitleEvent { title: "set directly".to_string() }));
+
+    assert!(vterm.set_altscreen(true));
+    assert_eq!(try_recv_alt_screen_event(&rx), Some(AltScreenEvent { is_on: true }));
+
+    assert!(vterm.set_mouse_mode(MouseMode::Drag));
+    assert_eq!(try_recv_mouse_event(&rx), Some(MouseEvent { mode: MouseMode::Drag }));
+}
+
+#[test]
+fn screen_term_props_are_refused_when_they_cant_be_set() {
+    let mut vterm = term_prop_vterm();
+
+    // The screen has no alternate buffer to switch to
+    assert!(!vterm.set_altscreen(true));
+    assert!(!vterm.set_title("nul\0byte"));
+}
+
+#[test]
+fn screen_events_work_after_vterm_is_moved() {
+    fn build_vterm() -> VTerm {
+        let mut vterm: VTerm = VTerm::new(&Size {
+            height: 2,
+            width: 4,
+        });
+        vterm.screen_receive_events(&ScreenCallbacksConfig::all());
+        vterm.screen_set_damage_merge(DamageSize::Screen);
+        vterm
+    }
+
+    let mut vterms = vec![];
+    vterms.push(build_vterm());
+    let mut vterm = vterms.pop().unwrap();
+
+    vterm.write(b"abc").unwrap();
+    vterm.flush().unwrap();
+
+    let rx = vterm.screen_event_rx.take().unwrap();
+    let event = try_recv_damage_event(&rx);
+
+    assert!(event.is_some());
+    let event = event.unwrap();
+    assert_eq!(event.rect, Rect::new(Pos::new(0,0), Size::new(3,1)));
+}
+
+struct DamageRecorder {
+    rects: Rc<RefCell<Vec<Rect>>>,
+}
+
+impl ScreenHandler for DamageRecorder {
+    fn damage(&mut self, rect: Rect) -> bool {
+        self.rects.borrow_mut().push(rect);
+        true
+    }
+}
+
+#[test]
+fn screen_handler_is_called_during_write() {
+    let mut vterm: VTerm = VTerm::new(&Size {
+        height: 2,
+        width: 4,
+    });
+    let rects = Rc::new(RefCell::new(vec![]));
+    vterm.set_screen_handler(Box::new(DamageRecorder { rects: rects.clone() }));
+    vterm.screen_set_damage_merge(DamageSize::Screen);
+
+    vterm.write(b"abc").unwrap();
+    vterm.flush().unwrap();
+
+    assert_eq!(*rects.borrow(),
+               vec![Rect::new(Pos::new(0, 0), Size::new(3, 1))]);
+}
+
+#[test]
+fn screen_handler_defaults_let_the_state_change_term_props() {
+    let mut vterm: VTerm = VTerm::new(&Size {
+        height: 2,
+        width: 4,
+    });
+    vterm.screen_enable_altscreen(true);
+    vterm.set_screen_handler(Box::new(DamageRecorder { rects: Rc::new(RefCell::new(vec![])) }));
+
+    // Hide the cursor and switch to the alt screen, then ask whether the state noticed
+    vterm.write(b"\x1b[?25l\x1b[?1047h\x1b[?25$p\x1b[?1047$p").unwrap();
+
+    let mut output = vec![];
+    vterm.read_to_end(&mut output).unwrap();
+    assert_eq!(output, b"\x1b[?25;2$y\x1b[?1047;1$y");
+}
+
+struct PanickingHandler;
+
+impl ScreenHandler for PanickingHandler {
+    fn bell(&mut self) -> bool {
+        panic!("bell");
+    }
+}
+
+#[test]
+fn screen_handler_panics_are_reported_after_write() {
+    let mut vterm: VTerm = VTerm::new(&Size {
+        height: 2,
+        width: 4,
+    });
+    vterm.set_screen_handler(Box::new(PanickingHandler));
+
+    vterm.write(b"a").unwrap();
+    assert!(vterm.take_error().is_none());
+
+    // The bytes are used even though the handler panicked
+    assert_eq!(vterm.write(b"\x07b").unwrap(), 2);
+    assert_eq!(vterm.take_error(),
+               Some(Error::CallbackPanicked("bell".to_string())));
+    assert_eq!(vterm.screen_get_cell(&Pos::new(1, 0)).text(), "b");
+
+    vterm.write(b"\x07").unwrap();
+    assert!(vterm.flush().is_err());
+    assert!(vterm.flush().is_ok());
+}
+
+fn recv_clipboard_events(rx: &Receiver<ScreenEvent>) -> Vec<ScreenEvent> {
+    let mut events = vec![];
+    while let Ok(e) = rx.try_recv() {
+        match e {
+            ScreenEvent::ClipboardSet { .. } |
+            ScreenEvent::ClipboardQuery { .. } => events.push(e),
+            _ => {}
+        }
+    }
+    events
+}
+
+fn clipboard_vterm(policy: ClipboardPolicy) -> VTerm {
+    let mut vterm: VTerm = VTerm::new(&Size {
+        height: 2,
+        width: 4,
+    });
+    vterm.screen_receive_events(&ScreenCallbacksConfig::all());
+    vterm.set_clipboard_policy(policy);
+    vterm
+}
+
+#[test]
+fn screen_clipboard_is_denied_by_default() {
+    let mut vterm: VTerm = VTerm::new(&Size {
+        height: 2,
+        width: 4,
+    });
+    vterm.screen_receive_events(&ScreenCallbacksConfig::all());
+    assert_eq!(vterm.clipboard_policy(), ClipboardPolicy::Deny);
+
+    vterm.write(b"\x1b]52;c;Zm9v\x07\x1b]52;c;?\x07").unwrap();
+
+    let rx = vterm.screen_event_rx.take().unwrap();
+    assert_eq!(recv_clipboard_events(&rx).len(), 0);
+    assert_eq!(vterm.output_pending(), 0);
+}
+
+#[test]
+fn screen_can_generate_clipboard_set_events() {
+    let mut vterm = clipboard_vterm(ClipboardPolicy::AllowWrite);
+
+    vterm.write(b"\x1b]52;c;Zm9vYmFy\x07\x1b]52;p;not base64!\x07\x1b]52;s0;?\x1b\\").unwrap();
+
+    let rx = vterm.screen_event_rx.take().unwrap();
+    let events = recv_clipboard_events(&rx);
+    assert_eq!(events.len(), 1);
+    match events[0] {
+        ScreenEvent::ClipboardSet { ref selection, ref data } => {
+            assert_eq!(selection, "c");
+            assert_eq!(data, b"foobar");
+        }
+        _ => panic!("expected a clipboard set event"),
+    }
+    assert_eq!(vterm.output_pending(), 0);
+}
+
+#[test]
+fn screen_clipboard_set_can_be_split_across_writes() {
+    let mut vterm = clipboard_vterm(ClipboardPolicy::AllowWrite);
+    let sequence = format!("\x1b]52;c;{}\x07", (0..20).map(|_| "Zm9vYmFy").collect::<String>());
+
+    let (start, end) = sequence.as_bytes().split_at(40);
+    vterm.write(start).unwrap();
+    vterm.write(end).unwrap();
+
+    let rx = vterm.screen_event_rx.take().unwrap();
+    let events = recv_clipboard_events(&rx);
+    assert_eq!(events.len(), 1);
+    match events[0] {
+        ScreenEvent::ClipboardSet { ref data, .. } => {
+            assert_eq!(data.len(), 120);
+            assert!(data.chunks(6).all(|chunk| chunk == b"foobar"));
+        }
+        _ => panic!("expected a clipboard set event"),
+    }
+}
+
+#[test]
+fn screen_clipboard_set_can_end_with_st_split_across_writes() {
+    let mut vterm = clipboard_vterm(ClipboardPolicy::AllowWrite);
+
+    vterm.write(b"\x1b]52;c;Zm9v\x1b").unwrap();
+    vterm.write(b"\\").unwrap();
+
+    let rx = vterm.screen_event_rx.take().unwrap();
+    let events = recv_clipboard_events(&rx);
+    assert_eq!(events.len(), 1);
+    match events[0] {
+        ScreenEvent::ClipboardSet { ref data, .. } => assert_eq!(data, b"foo"),
+        _ => panic!("expected a clipboard set event"),
+    }
+}
+
+#[test]
+fn screen_clipboard_set_that_is_too_long_is_dropped() {
+    let mut vterm = clipboard_vterm(ClipboardPolicy::AllowWrite);
+    let sequence = format!("\x1b]52;c;{}\x07", (0..300_000).map(|_| "AAAA").collect::<String>());
+
+    let (start, end) = sequence.as_bytes().split_at(40);
+    vterm.write(start).unwrap();
+    vterm.write(end).unwrap();
+    vterm.write(b"\x1b]52;c;Zm9v\x07").unwrap();
+
+    let rx = vterm.screen_event_rx.take().unwrap();
+    let events = recv_clipboard_events(&rx);
+    assert_eq!(events.len(), 1);
+    match events[0] {
+        ScreenEvent::ClipboardSet { ref data, .. } => assert_eq!(data, b"foo"),
+        _ => panic!("expected a clipboard set event"),
+    }
+}
+
+#[test]
+fn screen_can_generate_clipboard_query_events() {
+    let mut vterm = clipboard_vterm(ClipboardPolicy::AllowReadWrite);
+
+    vterm.write(b"\x1b]52;c;?\x07").unwrap();
+
+    let rx = vterm.screen_event_rx.take().unwrap();
+    let events = recv_clipboard_events(&rx);
+    assert_eq!(events.len(), 1);
+    match events[0] {
+        ScreenEvent::ClipboardQuery { ref selection } => assert_eq!(selection, "c"),
+        _ => panic!("expected a clipboard query event"),
+    }
+
+    vterm.clipboard_reply("c", b"foobar");
+    let mut output = vec![];
+    vterm.read_to_end(&mut output).unwrap();
+    assert_eq!(output, b"\x1b]52;c;Zm9vYmFy\x1b\\");
+}
+
+struct Clipboard;
+
+impl ScreenHandler for Clipboard {
+    fn clipboard_query(&mut self, selection: String) -> Option<Vec<u8>> {
+        Some(format!("from {}", selection).into_bytes())
+    }
+}
+
+#[test]
+fn screen_handler_can_answer_clipboard_queries() {
+    let mut vterm: VTerm = VTerm::new(&Size {
+        height: 2,
+        width: 4,
+    });
+    vterm.set_screen_handler(Box::new(Clipboard));
+    vterm.set_clipboard_policy(ClipboardPolicy::AllowReadWrite);
+
+    vterm.write(b"\x1b]52;p;?\x07").unwrap();
+
+    let mut output = vec![];
+    vterm.read_to_end(&mut output).unwrap();
+    assert_eq!(output, b"\x1b]52;p;ZnJvbSBw\x1b\\");
+}
+
+#[test]
+fn screen_can_generate_working_directory_events() {
+    let mut vterm: VTerm = VTerm::new(&Size {
+        height: 2,
+        width: 4,
+    });
+    vterm.screen_receive_events(&ScreenCallbacksConfig::all());
+
+    vterm.write(b"\x1b]7;file://box/home/me/my%20dir\x07").unwrap();
+    let rx = vterm.screen_event_rx.take().unwrap();
+    assert_eq!(try_recv_working_directory_event(&rx),
+               Some(WorkingDirectoryEvent {
+                   host: "box".to_string(),
+                   path: "/home/me/my dir".to_string(),
+               }));
+
+    vterm.screen_event_rx = Some(rx);
+    vterm.write(b"\x1b]7;file:///tmp\x07\x1b]7;http://box/tmp\x07").unwrap();
+    let rx = vterm.screen_event_rx.take().unwrap();
+    assert_eq!(try_recv_working_directory_event(&rx),
+               Some(WorkingDirectoryEvent {
+                   host: "".to_string(),
+                   path: "/tmp".to_string(),
+               }));
+    assert_eq!(try_recv_working_directory_event(&rx), None);
+}
+
+#[test]
+fn screen_can_generate_notification_events() {
+    let mut vterm: VTerm = VTerm::new(&Size {
+        height: 2,
+        width: 4,
+    });
+    vterm.screen_receive_events(&ScreenCallbacksConfig::all());
+    let (tx, unhandled_rx) = ::std::sync::mpsc::channel();
+    vterm.set_unhandled_sequence_handler(Box::new(ChannelUnhandledSequenceHandler::new(tx)));
+
+    vterm.write(b"\x1b]9;build done\x07\x1b]9;4;1;50\x07").unwrap();
+    vterm.write(b"\x1b]777;notify;make;build done\x07\x1b]777;other\x07").unwrap();
+
+    let rx = vterm.screen_event_rx.take().unwrap();
+    assert_eq!(try_recv_notification_event(&rx),
+               Some(NotificationEvent {
+                   title: None,
+                   body: "build done".to_string(),
+               }));
+    assert_eq!(try_recv_notification_event(&rx),
+               Some(NotificationEvent {
+                   title: Some("make".to_string()),
+                   body: "build done".to_string(),
+               }));
+    assert_eq!(try_recv_notification_event(&rx), None);
+
+    // ConEmu's OSC 9 commands and other OSC 777 commands aren't notifications
+    assert_eq!(unhandled_rx.try_recv().unwrap(),
+               UnhandledSequence::Osc(b"9;4;1;50".to_vec()));
+    assert_eq!(unhandled_rx.try_recv().unwrap(),
+               UnhandledSequence::Osc(b"777;other".to_vec()));
+}
+
 // Builds a function that returns a Some of the first event of the given type found on the channel
 // or None.
 macro_rules! dry {
@@ -157,3 +614,15 @@ dry!(try_recv_move_rect_event, MoveRectEvent, ScreenEvent::MoveRect);
 dry!(try_recv_move_cursor_event, MoveCursorEvent, ScreenEvent::MoveCursor);
 dry!(try_recv_alt_screen_event, AltScreenEvent, ScreenEvent::AltScreen);
 dry!(try_recv_cursor_blink_event, CursorBlinkEvent, ScreenEvent::CursorBlink);
+dry!(try_recv_cursor_visible_event,
+     CursorVisibleEvent,
+     ScreenEvent::CursorVisible);
+dry!(try_recv_cursor_shape_event, CursorShapeEvent, ScreenEvent::CursorShape);
+dry!(try_recv_title_event, TitleEvent, ScreenEvent::Title);
+dry!(try_recv_icon_name_event, IconNameEvent, ScreenEvent::IconName);
+dry!(try_recv_reverse_event, ReverseEvent, ScreenEvent::Reverse);
+dry!(try_recv_mouse_event, MouseEvent, ScreenEvent::Mouse);
+dry!(try_recv_working_directory_event,
+     WorkingDirectoryEvent,
+     ScreenEvent::WorkingDirectory);
+dry!(try_recv_notification_event, NotificationEvent, ScreenEvent::Notification);
@@ -0,0 +1,56 @@
+use std::io::prelude::*;
+use vterm_sys::*;
+
+fn line_text(line: &[ScreenCell]) -> String {
+    line.iter().filter(|cell| cell.chars.len() > 0).map(|cell| cell.chars[0]).collect()
+}
+
+#[test]
+fn scrollback_collects_lines_scrolled_off_the_screen() {
+    let mut vterm: VTerm = VTerm::new(&Size {
+        height: 2,
+        width: 3,
+    });
+    vterm.write(b"one\r\ntwo\r\nsix\r\nten").unwrap();
+    vterm.flush().unwrap();
+
+    assert_eq!(vterm.scrollback().len(), 2);
+    assert_eq!(line_text(vterm.scrollback().line(0).unwrap()), "two");
+    assert_eq!(line_text(vterm.scrollback().line(1).unwrap()), "one");
+    assert!(vterm.scrollback().line(2).is_none());
+}
+
+#[test]
+fn scrollback_lines_come_back_when_the_screen_grows() {
+    let mut vterm: VTerm = VTerm::new(&Size {
+        height: 2,
+        width: 3,
+    });
+    vterm.write(b"one\r\ntwo\r\nsix\r\nten").unwrap();
+    vterm.flush().unwrap();
+
+    vterm.set_size(&Size {
+        height: 3,
+        width: 4,
+    });
+
+    assert_eq!(vterm.scrollback().len(), 1);
+    assert_eq!(vterm.screen_get_text_lossy(&Rect::new(Pos::new(0, 0), Size::new(4, 3))),
+               "two\nsix\nten");
+}
+
+#[test]
+fn scrollback_keeps_at_most_the_limit() {
+    let mut vterm: VTerm = VTerm::new(&Size {
+        height: 2,
+        width: 3,
+    });
+    vterm.set_scrollback_limit(2);
+    vterm.write(b"one\r\ntwo\r\nsix\r\nten\r\nend").unwrap();
+    vterm.flush().unwrap();
+
+    assert_eq!(vterm.scrollback().limit(), 2);
+    assert_eq!(vterm.scrollback().len(), 2);
+    assert_eq!(line_text(vterm.scrollback().line(0).unwrap()), "six");
+    assert_eq!(line_text(vterm.scrollback().line(1).unwrap()), "two");
+}
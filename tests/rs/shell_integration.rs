@@ -0,0 +1,142 @@
+use std::io::prelude::*;
+use vterm_sys::*;
+
+fn new_vterm(height: usize, width: usize) -> VTerm {
+    VTerm::new(&Size {
+        height: height,
+        width: width,
+    })
+}
+
+const PROMPT: &'static [u8] = b"\x1b]133;A\x07$ \x1b]133;B\x07";
+
+/// Write a prompt, a command and its output the way a shell with OSC 133 support does
+fn run_command(vterm: &mut VTerm, command: &[u8], output: &[u8], status: i32) {
+    vterm.write(PROMPT).unwrap();
+    vterm.write(command).unwrap();
+    vterm.write(b"\r\n\x1b]133;C\x07").unwrap();
+    vterm.write(output).unwrap();
+    vterm.write(format!("\x1b]133;D;{}\x07", status).as_bytes()).unwrap();
+}
+
+#[test]
+fn shell_integration_marks_are_sent_as_events() {
+    let mut vterm = new_vterm(5, 10);
+    vterm.screen_receive_events(&ScreenCallbacksConfig::all());
+    run_command(&mut vterm, b"ls", b"a\r\n", 1);
+
+    let rx = vterm.screen_event_rx.take().unwrap();
+    let mut marks = vec![];
+    while let Ok(event) = rx.try_recv() {
+        if let ScreenEvent::ShellMark { mark, row } = event {
+            marks.push((mark, row));
+        }
+    }
+
+    assert_eq!(marks,
+               vec![(ShellMark::PromptStart, 0),
+                    (ShellMark::CommandStart, 0),
+                    (ShellMark::OutputStart, 1),
+                    (ShellMark::CommandFinished { exit_status: Some(1) }, 2)]);
+}
+
+#[test]
+fn shell_integration_marks_are_recorded_against_rows() {
+    let mut vterm = new_vterm(5, 10);
+    run_command(&mut vterm, b"ls", b"a\r\n", 0);
+
+    assert_eq!(vterm.screen_row_marks(0),
+               &[RowMark {
+                     mark: ShellMark::PromptStart,
+                     col: 0,
+                 },
+                 RowMark {
+                     mark: ShellMark::CommandStart,
+                     col: 2,
+                 }]);
+    assert_eq!(vterm.screen_row_marks(1),
+               &[RowMark {
+                     mark: ShellMark::OutputStart,
+                     col: 0,
+                 }]);
+    assert!(vterm.screen_row_marks(3).is_empty());
+}
+
+#[test]
+fn shell_integration_finds_last_command_output() {
+    let mut vterm = new_vterm(10, 10);
+    run_command(&mut vterm, b"ls", b"one\r\ntwo\r\n", 0);
+    run_command(&mut vterm, b"ls -a", b".\r\none  \r\ntwo\r\n", 0);
+    vterm.write(PROMPT).unwrap();
+
+    assert_eq!(vterm.last_command_output(), Some(".\none\ntwo".to_string()));
+}
+
+#[test]
+fn shell_integration_finds_output_in_the_scrollback() {
+    let mut vterm = new_vterm(3, 10);
+    run_command(&mut vterm, b"seq 5", b"1\r\n2\r\n3\r\n4\r\n5\r\n", 0);
+    vterm.write(PROMPT).unwrap();
+
+    assert!(vterm.scrollback().len() > 0);
+    assert_eq!(vterm.last_command_output(), Some("1\n2\n3\n4\n5".to_string()));
+}
+
+#[test]
+fn shell_integration_has_no_output_until_a_command_finishes() {
+    let mut vterm = new_vterm(5, 10);
+    assert_eq!(vterm.last_command_output(), None);
+
+    vterm.write(PROMPT).unwrap();
+    vterm.write(b"sleep 1\r\n\x1b]133;C\x07").unwrap();
+    assert_eq!(vterm.last_command_output(), None);
+}
+
+#[test]
+fn shell_integration_marks_are_dropped_when_the_screen_is_cleared() {
+    let mut vterm = new_vterm(5, 10);
+    run_command(&mut vterm, b"ls", b"a\r\n", 0);
+    vterm.write(b"\x1b[2J").unwrap();
+
+    assert_eq!(vterm.last_command_output(), None);
+}
+
+#[test]
+fn shell_integration_marks_survive_the_prompt_being_redrawn() {
+    let mut vterm = new_vterm(5, 10);
+    run_command(&mut vterm, b"ls", b"a\r\n", 0);
+    // zsh and fish clear the line and everything below it before drawing the prompt
+    vterm.write(b"\r\x1b[K\x1b[J").unwrap();
+    vterm.write(PROMPT).unwrap();
+
+    assert_eq!(vterm.last_command_output(), Some("a".to_string()));
+}
+
+#[test]
+fn shell_integration_marks_are_dropped_by_a_reset() {
+    let mut vterm = new_vterm(5, 10);
+    run_command(&mut vterm, b"ls", b"a\r\n", 0);
+    vterm.write(b"\x1bc").unwrap();
+
+    assert_eq!(vterm.last_command_output(), None);
+}
+
+#[test]
+fn shell_integration_marks_are_dropped_by_clearing_from_home() {
+    let mut vterm = new_vterm(5, 10);
+    run_command(&mut vterm, b"ls", b"a\r\n", 0);
+    // What clear(1) writes, which libvterm erases as the first row and then the rest
+    vterm.write(b"\x1b[H\x1b[J").unwrap();
+
+    assert_eq!(vterm.last_command_output(), None);
+    assert!((0..5).all(|row| vterm.screen_row_marks(row).is_empty()));
+}
+
+#[test]
+fn shell_integration_marks_are_dropped_from_rows_erased_below_the_cursor() {
+    let mut vterm = new_vterm(5, 10);
+    run_command(&mut vterm, b"ls", b"a\r\n", 0);
+    vterm.write(b"\x1b[2H\x1b[J").unwrap();
+
+    assert_eq!(vterm.last_command_output(), None);
+}
@@ -0,0 +1,175 @@
+use std::io::prelude::*;
+use vterm_sys::*;
+
+fn new_vterm(height: usize, width: usize) -> VTerm {
+    VTerm::new(&Size {
+        height: height,
+        width: width,
+    })
+}
+
+#[test]
+fn state_knows_where_the_cursor_is() {
+    let mut vterm = new_vterm(5, 10);
+    assert_eq!(vterm.cursor_pos(), Pos::new(0, 0));
+
+    vterm.write(b"ab\r\ncde").unwrap();
+    assert_eq!(vterm.cursor_pos(), Pos::new(3, 1));
+
+    vterm.write(b"\x1b[5;8H").unwrap();
+    assert_eq!(vterm.cursor_pos(), Pos::new(7, 4));
+}
+
+#[test]
+fn state_knows_the_pen() {
+    let mut vterm = new_vterm(2, 2);
+    let (default_fg, default_bg) = vterm.state_get_default_colors();
+
+    let pen = vterm.pen();
+    assert!(!pen.bold && !pen.italic && !pen.blink && !pen.reverse && !pen.strike);
+    assert_eq!(pen.underline, Underline::None);
+    assert_eq!(pen.font, 0);
+    assert_eq!(pen.fg_rgb, default_fg);
+    assert_eq!(pen.bg_rgb, default_bg);
+
+    vterm.write(b"\x1b[1;3;4;5;7;9;12;31;42m").unwrap();
+    let pen = vterm.pen();
+    assert!(pen.bold && pen.italic && pen.blink && pen.reverse && pen.strike);
+    assert_eq!(pen.underline, Underline::Single);
+    assert_eq!(pen.font, 2);
+    assert_eq!(pen.fg_palette, Some(1));
+    assert_eq!(pen.fg_rgb, vterm.state_get_rgb_color_from_palette(1));
+    assert_eq!(pen.bg_palette, Some(2));
+    assert_eq!(pen.bg_rgb, vterm.state_get_rgb_color_from_palette(2));
+
+    vterm.write(b"\x1b[21m").unwrap();
+    assert_eq!(vterm.pen().underline, Underline::Double);
+
+    vterm.write(b"\x1b[m").unwrap();
+    assert!(!vterm.pen().bold);
+}
+
+#[test]
+fn state_palette_can_be_read_and_set() {
+    let mut vterm = new_vterm(2, 2);
+    let mut palette = vterm.palette();
+    assert_eq!(palette.colors.len(), 256);
+    assert_eq!(palette.colors[1], vterm.state_get_rgb_color_from_palette(1));
+
+    palette.load_xresources("*.color1: #cc241d\n*.foreground: #ebdbb2\n*.background: #282828")
+        .unwrap();
+    vterm.set_palette(&palette);
+    assert_eq!(vterm.palette(), palette);
+
+    vterm.write(b"\x1b[31ma\x1b[mb").unwrap();
+    let cells = vterm.screen_get_cells_in_rect(&Rect::new(Pos::new(0, 0), Size::new(2, 1)));
+    assert_eq!(cells[0].fg_rgb,
+               ColorRGB {
+                   red: 0xcc,
+                   green: 0x24,
+                   blue: 0x1d,
+               });
+    assert_eq!(cells[1].bg_rgb,
+               ColorRGB {
+                   red: 0x28,
+                   green: 0x28,
+                   blue: 0x28,
+               });
+}
+
+#[test]
+fn state_palette_indexes_follow_palette_changes() {
+    let mut vterm = new_vterm(2, 2);
+    let old_red = vterm.state_get_rgb_color_from_palette(1);
+    assert_eq!(vterm.state_get_palette_color_from_rgb(&old_red), Some(1));
+
+    let mut palette = vterm.palette();
+    palette.colors[1] = ColorRGB {
+        red: 1,
+        green: 2,
+        blue: 3,
+    };
+    vterm.set_palette(&palette);
+
+    vterm.write(b"\x1b[31ma").unwrap();
+    let cell = vterm.screen_get_cell(&Pos::new(0, 0));
+    assert_eq!(cell.fg_palette, Some(1));
+    assert_eq!(vterm.state_get_palette_color_from_rgb(&palette.colors[1]), Some(1));
+    assert_eq!(vterm.state_get_palette_color_from_rgb(&old_red), None);
+}
+
+#[test]
+fn state_colors_outside_the_palette_have_no_index() {
+    let mut vterm = new_vterm(2, 2);
+    let (default_fg, _) = vterm.state_get_default_colors();
+    assert_eq!(vterm.state_get_palette_color_from_rgb(&default_fg), None);
+    assert_eq!(vterm.pen().fg_palette, None);
+
+    vterm.write(b"a\x1b[38;2;1;2;3mb").unwrap();
+    let cells = vterm.screen_get_cells_in_rect(&Rect::new(Pos::new(0, 0), Size::new(2, 1)));
+    assert_eq!(cells[0].fg_palette, None);
+    assert_eq!(cells[1].fg_rgb,
+               ColorRGB {
+                   red: 1,
+                   green: 2,
+                   blue: 3,
+               });
+    assert_eq!(cells[1].fg_palette, None);
+    assert_eq!(vterm.pen().fg_palette, None);
+}
+
+#[test]
+fn state_bold_can_be_highbright() {
+    let mut vterm = new_vterm(2, 2);
+    assert!(!vterm.bold_is_highbright());
+
+    vterm.write(b"\x1b[1;31ma").unwrap();
+    vterm.set_bold_is_highbright(true);
+    assert!(vterm.bold_is_highbright());
+    vterm.write(b"\x1b[m\x1b[1;31mb").unwrap();
+
+    let cells = vterm.screen_get_cells_in_rect(&Rect::new(Pos::new(0, 0), Size::new(2, 1)));
+    assert_eq!(cells[0].fg_rgb, vterm.state_get_rgb_color_from_palette(1));
+    assert_eq!(cells[1].fg_rgb, vterm.state_get_rgb_color_from_palette(9));
+    assert_eq!(cells[1].fg_palette, Some(9));
+}
+
+#[test]
+fn state_knows_the_line_info_of_each_row() {
+    let mut vterm = new_vterm(3, 4);
+    // DECDWL on the first row, then DECDHL bottom half on the last
+    vterm.write(b"\x1b#6\x1b[3H\x1b#4").unwrap();
+
+    assert_eq!(vterm.line_info(0),
+               LineInfo {
+                   double_width: true,
+                   double_height: DoubleHeight::None,
+               });
+    assert_eq!(vterm.line_info(1), LineInfo::default());
+    assert_eq!(vterm.line_info(2),
+               LineInfo {
+                   double_width: true,
+                   double_height: DoubleHeight::Bottom,
+               });
+    assert!(vterm.try_line_info(3).is_err());
+}
+
+#[test]
+fn state_sequences_after_a_cancelled_long_string_still_work() {
+    let mut vterm = new_vterm(2, 2);
+    let payload = (0..1024).map(|_| "A").collect::<String>();
+    vterm.write(b"\x1b]52;c;").unwrap();
+    for _ in 0..1100 {
+        vterm.write(payload.as_bytes()).unwrap();
+    }
+    vterm.write(b"\x18\x1b[31m").unwrap();
+    assert_eq!(vterm.pen().fg_palette, Some(1));
+
+    // An ESC that isn't ST breaks off the string too
+    vterm.write(b"\x1bP").unwrap();
+    for _ in 0..1100 {
+        vterm.write(payload.as_bytes()).unwrap();
+    }
+    vterm.write(b"\x1b[32m").unwrap();
+    assert_eq!(vterm.pen().fg_palette, Some(2));
+}
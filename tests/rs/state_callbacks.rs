@@ -3,6 +3,8 @@ use vterm_sys::*;
 use term::terminfo::TermInfo;
 use ::support::CapBuilder;
 use std::sync::mpsc::Receiver;
+use std::rc::Rc;
+use std::cell::RefCell;
 
 #[test]
 fn state_can_generate_put_glyph_events() {
@@ -18,11 +20,34 @@ fn state_can_generate_put_glyph_events() {
 
     assert!(event.is_some());
     let event = event.unwrap();
-    assert_eq!(event.glyph_info.chars[0], b'a');
+    assert_eq!(event.glyph_info.chars[0], 'a');
     assert_eq!(event.pos.x, 0);
     assert_eq!(event.pos.y, 0);
 }
 
+#[test]
+fn state_put_glyph_events_have_protection_and_line_info() {
+    let mut vterm: VTerm = VTerm::new(&Size {
+        height: 2,
+        width: 4,
+    });
+    vterm.state_receive_events(&StateCallbacksConfig::all());
+    let rx = vterm.state_event_rx.take().unwrap();
+
+    vterm.write(b"a").unwrap();
+    let info = try_recv_put_glyph_event(&rx).unwrap().glyph_info;
+    assert_eq!(info.protected_cell, false);
+    assert_eq!(info.dwl, false);
+    assert_eq!(info.dhl, 0);
+
+    // DECSCA, then DECDHL bottom half
+    vterm.write(b"\x1b[1\"q\x1b#4b").unwrap();
+    let info = try_recv_put_glyph_event(&rx).unwrap().glyph_info;
+    assert_eq!(info.protected_cell, true);
+    assert_eq!(info.dwl, true);
+    assert_eq!(info.dhl, 2);
+}
+
 #[test]
 fn state_can_generate_move_cursor_events() {
     let mut vterm: VTerm = VTerm::new(&Size {
@@ -233,7 +258,57 @@ fn state_can_generate_pen_background_events() {
     assert!(event.is_some());
     let event = event.unwrap();
     // Why wouldn't this be 1?
-    assert_eq!(event.palette, 4);
+    assert_eq!(event.palette, Some(4));
+}
+
+#[test]
+fn state_pen_foreground_events_are_bright_for_bold_when_highbright() {
+    let mut vterm: VTerm = VTerm::new(&Size {
+        height: 2,
+        width: 2,
+    });
+    vterm.state_receive_events(&StateCallbacksConfig::all());
+    let rx = vterm.state_event_rx.take().unwrap();
+
+    vterm.write(b"\x1b[1;31m").unwrap();
+    assert_eq!(try_recv_pen_foreground_event(&rx).unwrap().palette, Some(1));
+
+    vterm.write(b"\x1b[m").unwrap();
+    while let Ok(_) = rx.try_recv() {}
+
+    vterm.set_bold_is_highbright(true);
+    vterm.write(b"\x1b[1;31m").unwrap();
+    let event = try_recv_pen_foreground_event(&rx).unwrap();
+    assert_eq!(event.palette, Some(9));
+    assert_eq!(event.rgb, vterm.state_get_rgb_color_from_palette(9));
+}
+
+#[test]
+fn state_can_generate_line_info_events() {
+    let mut vterm: VTerm = VTerm::new(&Size {
+        height: 2,
+        width: 4,
+    });
+    vterm.state_receive_events(&StateCallbacksConfig::all());
+    let rx = vterm.state_event_rx.take().unwrap();
+
+    // DECDHL top half
+    vterm.write(b"\x1b[2H\x1b#3").unwrap();
+    assert_eq!(try_recv_line_info_event(&rx),
+               Some(LineInfoEvent {
+                   row: 1,
+                   new: LineInfo {
+                       double_width: true,
+                       double_height: DoubleHeight::Top,
+                   },
+                   old: LineInfo::default(),
+               }));
+
+    // DECDWL
+    vterm.write(b"\x1b#6").unwrap();
+    let event = try_recv_line_info_event(&rx).unwrap();
+    assert_eq!(event.new.double_height, DoubleHeight::None);
+    assert_eq!(event.old.double_height, DoubleHeight::Top);
 }
 
 #[test]
@@ -356,7 +431,7 @@ fn state_can_generate_pen_foreground_events() {
     assert!(event.is_some());
     let event = event.unwrap();
     // Why isn't this 3? Seems like my xterm terminfo is putting my values out of sequence.
-    assert_eq!(event.palette, 6);
+    assert_eq!(event.palette, Some(6));
 }
 
 #[test]
@@ -785,6 +860,73 @@ fn state_can_generate_resize_events() {
     assert_eq!(event.size, Size::new(2,3));
 }
 
+#[test]
+fn state_events_work_after_vterm_is_moved() {
+    fn build_vterm() -> VTerm {
+        let mut vterm: VTerm = VTerm::new(&Size {
+            height: 2,
+            width: 2,
+        });
+        vterm.state_receive_events(&StateCallbacksConfig::all());
+        vterm
+    }
+
+    let mut vterms = vec![];
+    vterms.push(build_vterm());
+    let mut vterm = vterms.pop().unwrap();
+
+    vterm.write(b"a").unwrap();
+
+    let rx = vterm.state_event_rx.take().unwrap();
+    let event = try_recv_put_glyph_event(&rx);
+
+    assert!(event.is_some());
+    let event = event.unwrap();
+    assert_eq!(event.glyph_info.chars[0], 'a');
+}
+
+struct GlyphRecorder {
+    glyphs: Rc<RefCell<Vec<(char, Pos)>>>,
+}
+
+impl StateHandler for GlyphRecorder {
+    fn put_glyph(&mut self, glyph_info: &GlyphInfo, pos: Pos) -> bool {
+        self.glyphs.borrow_mut().push((glyph_info.chars[0], pos));
+        true
+    }
+}
+
+#[test]
+fn state_handler_is_called_during_write() {
+    let mut vterm: VTerm = VTerm::new(&Size {
+        height: 2,
+        width: 2,
+    });
+    let glyphs = Rc::new(RefCell::new(vec![]));
+    vterm.set_state_handler(Box::new(GlyphRecorder { glyphs: glyphs.clone() }));
+
+    vterm.write(b"ab").unwrap();
+
+    assert_eq!(*glyphs.borrow(),
+               vec![('a', Pos::new(0, 0)), ('b', Pos::new(1, 0))]);
+}
+
+#[test]
+fn state_handler_defaults_let_the_state_change_line_info() {
+    let mut vterm: VTerm = VTerm::new(&Size {
+        height: 2,
+        width: 4,
+    });
+    vterm.set_state_handler(Box::new(GlyphRecorder { glyphs: Rc::new(RefCell::new(vec![])) }));
+
+    vterm.write(b"\x1b#6\x1b[?25l\x1b[?25$p").unwrap();
+
+    assert!(vterm.line_info(0).double_width);
+    let mut output = vec![];
+    vterm.read_to_end(&mut output).unwrap();
+    assert_eq!(output, b"\x1b[?25;2$y");
+}
+
 // Builds a function that returns a Some of the first event of the given type found on the channel
 // or None.
 macro_rules! dry {
@@ -811,7 +953,7 @@ dry!(try_recv_cursor_visible_event, CursorVisibleEvent, StateEvent::CursorVisibl
 dry!(try_recv_erase_event, EraseEvent, StateEvent::Erase);
 dry!(try_recv_icon_name_event, IconNameEvent, StateEvent::IconName);
 dry!(try_recv_init_pen_event, InitPenEvent, StateEvent::InitPen);
-//dry!(try_recv_line_info_event, LineInfoEvent, StateEvent::LineInfo);
+dry!(try_recv_line_info_event, LineInfoEvent, StateEvent::LineInfo);
 dry!(try_recv_mouse_event, MouseEvent, StateEvent::Mouse);
 dry!(try_recv_move_cursor_event, MoveCursorEvent, StateEvent::MoveCursor);
 dry!(try_recv_move_rect_event, MoveRectEvent, StateEvent::MoveRect);
@@ -829,3 +971,24 @@ dry!(try_recv_resize_event, ResizeEvent, StateEvent::Resize);
 dry!(try_recv_reverse_event, ReverseEvent, StateEvent::Reverse);
 dry!(try_recv_scroll_rect_event, ScrollRectEvent, StateEvent::ScrollRect);
 dry!(try_recv_title_event, TitleEvent, StateEvent::Title);
+
+#[test]
+fn callbacks_survive_hostile_input() {
+    let mut vterm: VTerm = VTerm::new(&Size {
+        height: 5,
+        width: 5,
+    });
+    vterm.state_receive_events(&StateCallbacksConfig::all());
+    vterm.screen_receive_events(&ScreenCallbacksConfig::all());
+
+    let mut bytes: Vec<u8> = (0..256).map(|b| b as u8).collect();
+    bytes.extend_from_slice(b"\x1b[99999999999;-1;;;:::m\x1b[4:9m\x1b[21m\x1b[38:2:300:300:300m");
+    bytes.extend_from_slice(b"\x1b[9999 q\x1b[?9999h\x1b[?1003h\x1b[?1006h\x1b[?1049h");
+    bytes.extend_from_slice(b"\x1b]2;\xff\xfe\x07\x1b]1;\x1b\\\x1bP$q\x1b\\\x1b#8\x1b#3\x1b#6");
+    bytes.extend_from_slice(b"\x1b[1;99999r\x1b[99999;99999H\x1b[99999L\x1b[99999S\x1b[99999T");
+
+    for _ in 0..3 {
+        vterm.write(&bytes).unwrap();
+        vterm.flush().unwrap();
+    }
+}
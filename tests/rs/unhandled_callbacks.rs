@@ -0,0 +1,79 @@
+use std::io::prelude::*;
+use vterm_sys::*;
+use std::rc::Rc;
+use std::cell::RefCell;
+
+fn unhandled(input: &[u8]) -> Vec<UnhandledSequence> {
+    let mut vterm: VTerm = VTerm::new(&Size {
+        height: 2,
+        width: 2,
+    });
+    vterm.unhandled_sequence_receive_events();
+    vterm.write(input).unwrap();
+
+    let rx = vterm.unhandled_sequence_rx.take().unwrap();
+    let mut seqs = vec![];
+    while let Ok(seq) = rx.try_recv() {
+        seqs.push(seq);
+    }
+    seqs
+}
+
+#[test]
+fn unhandled_osc_is_received() {
+    assert_eq!(unhandled(b"\x1b]1337;SetMark\x07"),
+               vec![UnhandledSequence::Osc(b"1337;SetMark".to_vec())]);
+}
+
+#[test]
+fn handled_osc_is_not_received() {
+    assert_eq!(unhandled(b"\x1b]2;title\x07"), vec![]);
+}
+
+#[test]
+fn unhandled_csi_dcs_and_control_are_received() {
+    assert_eq!(unhandled(b"\x1b[>1;2:3z\x1bPzz\x1b\\\x01"),
+               vec![UnhandledSequence::Csi {
+                        leader: b">".to_vec(),
+                        args: vec![CsiArg::new(Some(1), false),
+                                   CsiArg::new(Some(2), true),
+                                   CsiArg::new(Some(3), false)],
+                        intermed: vec![],
+                        command: b'z',
+                    },
+                    UnhandledSequence::Dcs(b"zz".to_vec()),
+                    UnhandledSequence::Control(0x01)]);
+}
+
+struct OscRecorder {
+    seqs: Rc<RefCell<Vec<UnhandledSequence>>>,
+}
+
+impl UnhandledSequenceHandler for OscRecorder {
+    fn handle(&mut self, seq: UnhandledSequence) -> bool {
+        match seq {
+            UnhandledSequence::Osc(_) => {
+                self.seqs.borrow_mut().push(seq);
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+#[test]
+fn unhandled_sequence_handler_is_called_during_write() {
+    let mut vterm: VTerm = VTerm::new(&Size {
+        height: 2,
+        width: 2,
+    });
+    vterm.screen_receive_events(&ScreenCallbacksConfig::all());
+    let seqs = Rc::new(RefCell::new(vec![]));
+    vterm.set_unhandled_sequence_handler(Box::new(OscRecorder { seqs: seqs.clone() }));
+
+    vterm.write(b"\x1b]1337;SetUserVar=a=Yg==\x1b\\a").unwrap();
+
+    assert_eq!(*seqs.borrow(),
+               vec![UnhandledSequence::Osc(b"1337;SetUserVar=a=Yg==".to_vec())]);
+    assert_eq!(vterm.screen_get_cell(&Pos::new(0, 0)).chars[0], 'a');
+}